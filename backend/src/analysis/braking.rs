@@ -0,0 +1,267 @@
+// Braking event detection per frame and zone consistency across laps.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use super::tyres::{TyreEventDetector, Wheel, WHEELS};
+use crate::constants::LAP_HISTORY_CAP;
+use crate::model::State;
+
+// Hysteresis on the brake channel (0..1).
+const BRAKE_ON: f32 = 0.05;
+const BRAKE_OFF: f32 = 0.02;
+const MIN_START_SPEED_KPH: f32 = 30.0;
+// Applications that barely slow the car are taps, not braking events.
+const MIN_SPEED_DROP_KPH: f32 = 5.0;
+// Frames further apart than this (pause, packet loss) do not contribute to durations.
+const MAX_FRAME_GAP_MS: i32 = 250;
+const TRAIL_YAW_RATE: f32 = 0.15;
+const GRAVITY_MS2: f32 = 9.81;
+// Events from different laps within this distance are treated as the same zone.
+const ZONE_MATCH_M: f32 = 60.0;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BrakingEvent {
+    pub lap_number: Option<i16>,
+    pub start_distance_m: Option<f32>,
+    pub start_lap_time_ms: i32,
+    pub duration_ms: i32,
+    pub peak_brake: f32,
+    pub start_speed_kph: f32,
+    pub end_speed_kph: f32,
+    pub speed_drop_kph: f32,
+    pub peak_decel_g: f32,
+    pub trail_brake_ms: i32,
+    pub lockup_ms: i32,
+    pub lockup_wheels: Vec<Wheel>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LapBraking {
+    pub lap_id: u64,
+    pub lap_number: i16,
+    pub events: Vec<BrakingEvent>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BrakingZone {
+    pub zone: usize,
+    pub events: usize,
+    pub start_distance_m: f32,
+    pub start_distance_stddev_m: f32,
+    pub peak_brake_mean: f32,
+    pub peak_brake_stddev: f32,
+    pub end_speed_kph_mean: f32,
+    pub end_speed_kph_stddev: f32,
+    pub duration_ms_mean: f32,
+    pub duration_ms_stddev: f32,
+    pub lockup_laps: usize,
+}
+
+struct ActiveEvent {
+    event: BrakingEvent,
+    last_lap_time_ms: i32,
+    last_speed_kph: f32,
+    lockup_mask: [bool; 4],
+}
+
+pub struct BrakingAnalyzer {
+    active: Option<ActiveEvent>,
+    current: Vec<BrakingEvent>,
+    laps: VecDeque<LapBraking>,
+}
+
+impl BrakingAnalyzer {
+    pub fn new() -> Self {
+        Self {
+            active: None,
+            current: Vec::new(),
+            laps: VecDeque::with_capacity(LAP_HISTORY_CAP),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn laps(&self) -> &VecDeque<LapBraking> {
+        &self.laps
+    }
+
+    pub fn current_lap(&self) -> &[BrakingEvent] {
+        &self.current
+    }
+
+    // Lockups come from the tyre event detector, which must be updated first for this frame.
+    pub fn update(&mut self, state: &State, tyres: &TyreEventDetector) {
+        let (lap_time_ms, speed_kph) = match (state.current_lap_time_ms, state.speed_kph) {
+            (Some(lap_time_ms), Some(speed_kph)) => (lap_time_ms, speed_kph),
+            _ => return,
+        };
+        let brake = state.brake.unwrap_or(0.0);
+
+        let active = match self.active.as_mut() {
+            Some(active) => active,
+            None => {
+                if brake >= BRAKE_ON && speed_kph >= MIN_START_SPEED_KPH {
+                    self.active = Some(ActiveEvent {
+                        event: BrakingEvent {
+                            lap_number: state.current_lap,
+                            start_distance_m: state.lap_distance_m,
+                            start_lap_time_ms: lap_time_ms,
+                            duration_ms: 0,
+                            peak_brake: brake,
+                            start_speed_kph: speed_kph,
+                            end_speed_kph: speed_kph,
+                            speed_drop_kph: 0.0,
+                            peak_decel_g: 0.0,
+                            trail_brake_ms: 0,
+                            lockup_ms: 0,
+                            lockup_wheels: Vec::new(),
+                        },
+                        last_lap_time_ms: lap_time_ms,
+                        last_speed_kph: speed_kph,
+                        lockup_mask: [false; 4],
+                    });
+                }
+                return;
+            }
+        };
+
+        if brake < BRAKE_OFF {
+            self.close();
+            return;
+        }
+
+        let dt_ms = lap_time_ms - active.last_lap_time_ms;
+        if dt_ms > 0 && dt_ms <= MAX_FRAME_GAP_MS {
+            let event = &mut active.event;
+            event.duration_ms += dt_ms;
+            let decel_g =
+                (active.last_speed_kph - speed_kph) / 3.6 / (dt_ms as f32 / 1000.0) / GRAVITY_MS2;
+            event.peak_decel_g = event.peak_decel_g.max(decel_g);
+            if state
+                .yaw_rate
+                .is_some_and(|yaw| yaw.abs() >= TRAIL_YAW_RATE)
+            {
+                event.trail_brake_ms += dt_ms;
+            }
+            let locked = tyres.locked_wheels();
+            for (mask, locked) in active.lockup_mask.iter_mut().zip(locked) {
+                *mask |= locked;
+            }
+            if locked.contains(&true) {
+                event.lockup_ms += dt_ms;
+            }
+        }
+        if dt_ms >= 0 {
+            active.last_lap_time_ms = lap_time_ms;
+        }
+        active.last_speed_kph = speed_kph;
+        active.event.peak_brake = active.event.peak_brake.max(brake);
+        active.event.end_speed_kph = speed_kph;
+    }
+
+    pub fn finish_lap(&mut self, lap_id: u64, lap_number: i16) {
+        self.close();
+        if self.laps.len() >= LAP_HISTORY_CAP {
+            self.laps.pop_front();
+        }
+        self.laps.push_back(LapBraking {
+            lap_id,
+            lap_number,
+            events: std::mem::take(&mut self.current),
+        });
+    }
+
+    fn close(&mut self) {
+        let active = match self.active.take() {
+            Some(active) => active,
+            None => return,
+        };
+        let mut event = active.event;
+        event.speed_drop_kph = event.start_speed_kph - event.end_speed_kph;
+        if event.speed_drop_kph < MIN_SPEED_DROP_KPH {
+            return;
+        }
+        event.lockup_wheels = WHEELS
+            .iter()
+            .zip(active.lockup_mask.iter())
+            .filter(|(_, locked)| **locked)
+            .map(|(wheel, _)| *wheel)
+            .collect();
+        self.current.push(event);
+    }
+
+    pub fn zones(&self) -> Vec<BrakingZone> {
+        let mut events: Vec<(f32, &BrakingEvent)> = self
+            .laps
+            .iter()
+            .flat_map(|lap| lap.events.iter())
+            .filter_map(|event| event.start_distance_m.map(|distance| (distance, event)))
+            .collect();
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut clusters: Vec<Vec<&BrakingEvent>> = Vec::new();
+        let mut cluster_start = f32::MIN;
+        for (distance, event) in events {
+            match clusters.last_mut() {
+                Some(cluster) if distance - cluster_start <= ZONE_MATCH_M => cluster.push(event),
+                _ => {
+                    cluster_start = distance;
+                    clusters.push(vec![event]);
+                }
+            }
+        }
+
+        clusters
+            .into_iter()
+            .enumerate()
+            .map(|(idx, cluster)| {
+                let (start_mean, start_stddev) =
+                    mean_stddev(cluster.iter().filter_map(|event| event.start_distance_m));
+                let (peak_mean, peak_stddev) =
+                    mean_stddev(cluster.iter().map(|event| event.peak_brake));
+                let (end_mean, end_stddev) =
+                    mean_stddev(cluster.iter().map(|event| event.end_speed_kph));
+                let (duration_mean, duration_stddev) =
+                    mean_stddev(cluster.iter().map(|event| event.duration_ms as f32));
+                BrakingZone {
+                    zone: idx + 1,
+                    events: cluster.len(),
+                    start_distance_m: start_mean,
+                    start_distance_stddev_m: start_stddev,
+                    peak_brake_mean: peak_mean,
+                    peak_brake_stddev: peak_stddev,
+                    end_speed_kph_mean: end_mean,
+                    end_speed_kph_stddev: end_stddev,
+                    duration_ms_mean: duration_mean,
+                    duration_ms_stddev: duration_stddev,
+                    lockup_laps: cluster.iter().filter(|event| event.lockup_ms > 0).count(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for BrakingAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn mean_stddev(values: impl Iterator<Item = f32>) -> (f32, f32) {
+    let values: Vec<f32> = values.collect();
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let count = values.len() as f32;
+    let mean = values.iter().sum::<f32>() / count;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f32>()
+        / count;
+    (mean, variance.sqrt())
+}
@@ -0,0 +1,203 @@
+// Distance-aligned lap comparison on a common grid.
+
+use std::str::FromStr;
+
+use serde::Serialize;
+
+use super::laps::{LapPoint, LapTrace};
+
+const MAX_GRID_POINTS: usize = 20_000;
+const GRAVITY_MS2: f32 = 9.81;
+// Below this speed curvature from yaw rate is dominated by noise.
+const MIN_CURVATURE_SPEED_MS: f32 = 2.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LapRef {
+    Live(u64),
+    Recording {
+        name: String,
+        session_index: u64,
+        lap_number: i16,
+    },
+}
+
+impl LapRef {
+    pub fn recording_id(name: &str, session_index: u64, lap_number: i16) -> String {
+        format!("rec:{name}:{session_index}:{lap_number}")
+    }
+}
+
+impl FromStr for LapRef {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if let Some(rest) = value.strip_prefix("rec:") {
+            let invalid = || format!("invalid recording lap '{value}'");
+            let (rest, lap) = rest.rsplit_once(':').ok_or_else(invalid)?;
+            let (name, session) = rest.rsplit_once(':').ok_or_else(invalid)?;
+            let session_index = session
+                .parse::<u64>()
+                .map_err(|_| format!("invalid session index in '{value}'"))?;
+            let lap_number = lap
+                .parse::<i16>()
+                .map_err(|_| format!("invalid lap number in '{value}'"))?;
+            return Ok(LapRef::Recording {
+                name: name.to_string(),
+                session_index,
+                lap_number,
+            });
+        }
+        value
+            .parse::<u64>()
+            .map(LapRef::Live)
+            .map_err(|_| format!("invalid lap id '{value}'"))
+    }
+}
+
+#[derive(Serialize)]
+pub struct LapChannels {
+    pub time_ms: Vec<f32>,
+    pub speed_kph: Vec<f32>,
+    pub throttle: Vec<f32>,
+    pub brake: Vec<f32>,
+    pub gear: Vec<i8>,
+    pub rpm: Vec<f32>,
+    pub yaw_rate: Vec<f32>,
+    pub curvature: Vec<f32>,
+    pub lateral_g: Vec<f32>,
+    pub delta_ms: Vec<f32>,
+}
+
+#[derive(Serialize)]
+pub struct ComparedLap {
+    pub id: String,
+    pub lap_number: i16,
+    pub lap_time_ms: i32,
+    pub car_id: Option<i32>,
+    pub track_id: Option<i32>,
+    pub channels: LapChannels,
+}
+
+#[derive(Serialize)]
+pub struct LapComparison {
+    pub step_m: f32,
+    pub reference: String,
+    pub distance_m: Vec<f32>,
+    pub laps: Vec<ComparedLap>,
+}
+
+pub fn compare_laps(laps: &[(String, &LapTrace)], step_m: f32) -> Option<LapComparison> {
+    let monotonic: Vec<Vec<&LapPoint>> = laps
+        .iter()
+        .map(|(_, lap)| monotonic_points(&lap.points))
+        .collect();
+    let length_m = monotonic
+        .iter()
+        .map(|points| points.last().map(|point| point.distance_m).unwrap_or(0.0))
+        .fold(f32::MAX, f32::min);
+    if monotonic.iter().any(|points| points.len() < 2) || length_m <= 0.0 {
+        return None;
+    }
+
+    let step_m = step_m.max(length_m / MAX_GRID_POINTS as f32);
+    let count = (length_m / step_m).floor() as usize + 1;
+    let distance_m: Vec<f32> = (0..count).map(|idx| idx as f32 * step_m).collect();
+
+    let mut compared: Vec<ComparedLap> = laps
+        .iter()
+        .zip(monotonic.iter())
+        .map(|((id, lap), points)| ComparedLap {
+            id: id.clone(),
+            lap_number: lap.lap_number,
+            lap_time_ms: lap.lap_time_ms,
+            car_id: lap.car_id,
+            track_id: lap.track_id,
+            channels: resample(points, &distance_m),
+        })
+        .collect();
+
+    let reference_time = compared[0].channels.time_ms.clone();
+    for lap in compared.iter_mut() {
+        lap.channels.delta_ms = lap
+            .channels
+            .time_ms
+            .iter()
+            .zip(reference_time.iter())
+            .map(|(time, reference)| time - reference)
+            .collect();
+    }
+
+    Some(LapComparison {
+        step_m,
+        reference: laps[0].0.clone(),
+        distance_m,
+        laps: compared,
+    })
+}
+
+pub(super) fn monotonic_points(points: &[LapPoint]) -> Vec<&LapPoint> {
+    let mut out: Vec<&LapPoint> = Vec::with_capacity(points.len());
+    for point in points {
+        if out
+            .last()
+            .is_some_and(|last| point.distance_m <= last.distance_m)
+        {
+            continue;
+        }
+        out.push(point);
+    }
+    out
+}
+
+fn resample(points: &[&LapPoint], grid: &[f32]) -> LapChannels {
+    let mut channels = LapChannels {
+        time_ms: Vec::with_capacity(grid.len()),
+        speed_kph: Vec::with_capacity(grid.len()),
+        throttle: Vec::with_capacity(grid.len()),
+        brake: Vec::with_capacity(grid.len()),
+        gear: Vec::with_capacity(grid.len()),
+        rpm: Vec::with_capacity(grid.len()),
+        yaw_rate: Vec::with_capacity(grid.len()),
+        curvature: Vec::with_capacity(grid.len()),
+        lateral_g: Vec::with_capacity(grid.len()),
+        delta_ms: Vec::new(),
+    };
+
+    for distance in grid {
+        let idx = points
+            .partition_point(|point| point.distance_m < *distance)
+            .clamp(1, points.len() - 1);
+        let (a, b) = (points[idx - 1], points[idx]);
+        let span = b.distance_m - a.distance_m;
+        let t = if span > 0.0 {
+            ((distance - a.distance_m) / span).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+
+        let speed_kph = lerp(a.speed_kph, b.speed_kph);
+        let yaw_rate = lerp(a.yaw_rate, b.yaw_rate);
+        let speed_ms = speed_kph / 3.6;
+        channels
+            .time_ms
+            .push(lerp(a.lap_time_ms as f32, b.lap_time_ms as f32));
+        channels.speed_kph.push(speed_kph);
+        channels.throttle.push(lerp(a.throttle, b.throttle));
+        channels.brake.push(lerp(a.brake, b.brake));
+        channels.gear.push(if t < 0.5 { a.gear } else { b.gear });
+        channels.rpm.push(lerp(a.rpm, b.rpm));
+        channels.yaw_rate.push(yaw_rate);
+        channels
+            .curvature
+            .push(if speed_ms >= MIN_CURVATURE_SPEED_MS {
+                yaw_rate / speed_ms
+            } else {
+                0.0
+            });
+        channels.lateral_g.push(yaw_rate * speed_ms / GRAVITY_MS2);
+    }
+
+    channels
+}
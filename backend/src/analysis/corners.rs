@@ -0,0 +1,350 @@
+// Corner segmentation from centerline curvature and per-lap corner statistics.
+
+use serde::Serialize;
+
+use super::compare::monotonic_points;
+use super::laps::{LapPoint, LapTrace};
+use crate::meta::Centerline;
+
+const SAMPLE_STEP_M: f32 = 5.0;
+const SMOOTH_SAMPLES: usize = 4;
+// Hysteresis on curvature (1/m): enter a corner above ENTER, leave it below EXIT.
+const ENTER_CURVATURE: f32 = 1.0 / 200.0;
+const EXIT_CURVATURE: f32 = 1.0 / 330.0;
+const MIN_HEADING_CHANGE_RAD: f32 = 20.0 * std::f32::consts::PI / 180.0;
+const MERGE_GAP_M: f32 = 25.0;
+const BRAKE_ON: f32 = 0.05;
+// How far before corner entry a brake application still belongs to the corner.
+const BRAKE_LOOKBACK_M: f32 = 300.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentKind {
+    Corner,
+    Straight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnDirection {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TrackSegment {
+    pub kind: SegmentKind,
+    pub number: usize,
+    pub start_m: f32,
+    pub end_m: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<TurnDirection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_change_deg: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_radius_m: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CornerStats {
+    pub number: usize,
+    pub braking_point_m: Option<f32>,
+    pub peak_brake: f32,
+    pub entry_speed_kph: f32,
+    pub apex_speed_kph: f32,
+    pub apex_distance_m: f32,
+    pub apex_gear: i8,
+    pub exit_speed_kph: f32,
+    pub time_ms: f32,
+}
+
+// Lap distance of the centerline origin for this lap, so segments line up with lap distance.
+pub fn lap_origin_m(centerline: &Centerline, lap: &LapTrace) -> Option<f32> {
+    let first = lap.points.first()?;
+    let projection = centerline.project(first.pos_x, first.pos_z, None)?;
+    Some((projection.distance_m - first.distance_m).rem_euclid(centerline.length_m()))
+}
+
+pub fn segment_track(centerline: &Centerline, origin_m: f32) -> Vec<TrackSegment> {
+    let length_m = centerline.length_m();
+    let count = (length_m / SAMPLE_STEP_M).floor() as usize;
+    if count < SMOOTH_SAMPLES * 2 + 3 {
+        return Vec::new();
+    }
+    let step_m = length_m / count as f32;
+    let points: Vec<(f32, f32)> = (0..count)
+        .map(|idx| centerline.point_at(origin_m + idx as f32 * step_m))
+        .collect();
+
+    let headings: Vec<f32> = (0..count)
+        .map(|idx| {
+            let a = points[idx];
+            let b = points[(idx + 1) % count];
+            (b.1 - a.1).atan2(b.0 - a.0)
+        })
+        .collect();
+    // Positive heading change is counter-clockwise seen from above, i.e. a left turn.
+    let raw: Vec<f32> = (0..count)
+        .map(|idx| wrap_angle(headings[idx] - headings[(idx + count - 1) % count]) / step_m)
+        .collect();
+    let curvature: Vec<f32> = (0..count)
+        .map(|idx| {
+            let sum: f32 = (0..=SMOOTH_SAMPLES * 2)
+                .map(|offset| raw[(idx + count + offset - SMOOTH_SAMPLES) % count])
+                .sum();
+            sum / (SMOOTH_SAMPLES * 2 + 1) as f32
+        })
+        .collect();
+
+    // Scan from a point below the exit threshold so no corner is open across the wrap, and
+    // index past the end instead of wrapping: a corner through the lap origin stays whole.
+    let scan_start = curvature
+        .iter()
+        .position(|value| value.abs() < EXIT_CURVATURE)
+        .unwrap_or(0);
+    let at = |idx: usize| curvature[idx % count];
+    let mut corners: Vec<(usize, usize)> = Vec::new();
+    let mut open: Option<usize> = None;
+    for idx in scan_start..scan_start + count {
+        let value = at(idx);
+        match open {
+            None if value.abs() >= ENTER_CURVATURE => open = Some(idx),
+            Some(start) => {
+                let sign_flip =
+                    value.signum() != at(start).signum() && value.abs() >= EXIT_CURVATURE;
+                if value.abs() < EXIT_CURVATURE || sign_flip {
+                    corners.push((start, idx));
+                    open = if sign_flip && value.abs() >= ENTER_CURVATURE {
+                        Some(idx)
+                    } else {
+                        None
+                    };
+                }
+            }
+            None => {}
+        }
+    }
+    if let Some(start) = open {
+        corners.push((start, scan_start + count));
+    }
+
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in corners {
+        if let Some(last) = merged.last_mut() {
+            let same_direction = at(last.0).signum() == at(start).signum();
+            if same_direction && (start - last.1) as f32 * step_m <= MERGE_GAP_M {
+                last.1 = end;
+                continue;
+            }
+        }
+        merged.push((start, end));
+    }
+    if merged.len() > 1 {
+        let (first, last) = (merged[0], merged[merged.len() - 1]);
+        let same_direction = at(first.0).signum() == at(last.0).signum();
+        if same_direction && (first.0 + count - last.1) as f32 * step_m <= MERGE_GAP_M {
+            merged.remove(0);
+            if let Some(last) = merged.last_mut() {
+                last.1 = first.1 + count;
+            }
+        }
+    }
+
+    // Back to lap positions; a corner through the origin starts before it, at a negative index.
+    let mut kept: Vec<(isize, isize, f32)> = Vec::new();
+    for (start, end) in merged {
+        let heading_change: f32 = (start..end).map(|idx| raw[idx % count]).sum::<f32>() * step_m;
+        if heading_change.abs() < MIN_HEADING_CHANGE_RAD {
+            continue;
+        }
+        let shift = if end > count { count } else { 0 };
+        kept.push((
+            start as isize - shift as isize,
+            end as isize - shift as isize,
+            heading_change,
+        ));
+    }
+    kept.sort_by_key(|corner| corner.0);
+
+    let lap_end = count as isize + kept.first().map_or(0, |corner| corner.0.min(0));
+    let mut segments = Vec::new();
+    let mut cursor = 0isize;
+    let mut straight_number = 0usize;
+    for (idx, (start, end, heading_change)) in kept.into_iter().enumerate() {
+        if start > cursor {
+            straight_number += 1;
+            segments.push(straight(straight_number, cursor, start, step_m));
+        }
+        let peak = (start..end)
+            .map(|idx| curvature[idx.rem_euclid(count as isize) as usize])
+            .fold(0.0f32, |acc, value| acc.max(value.abs()));
+        segments.push(TrackSegment {
+            kind: SegmentKind::Corner,
+            number: idx + 1,
+            start_m: start as f32 * step_m,
+            end_m: end as f32 * step_m,
+            direction: Some(if heading_change > 0.0 {
+                TurnDirection::Left
+            } else {
+                TurnDirection::Right
+            }),
+            heading_change_deg: Some(heading_change.abs().to_degrees()),
+            min_radius_m: (peak > 0.0).then(|| 1.0 / peak),
+        });
+        cursor = end;
+    }
+    if cursor < lap_end {
+        straight_number += 1;
+        segments.push(straight(straight_number, cursor, lap_end, step_m));
+    }
+    segments
+}
+
+pub fn corner_stats(lap: &LapTrace, segments: &[TrackSegment]) -> Vec<CornerStats> {
+    let points = monotonic_points(&lap.points);
+    let mut stats = Vec::new();
+    let mut previous_end_m = 0.0f32;
+    for segment in segments {
+        if segment.kind != SegmentKind::Corner {
+            continue;
+        }
+        let lookback_start = (segment.start_m - BRAKE_LOOKBACK_M).max(previous_end_m);
+        previous_end_m = segment.end_m;
+
+        let inside: Vec<&LapPoint> = points
+            .iter()
+            .copied()
+            .filter(|point| {
+                point.distance_m >= segment.start_m && point.distance_m <= segment.end_m
+            })
+            .collect();
+        let apex = match inside
+            .iter()
+            .min_by(|a, b| a.speed_kph.total_cmp(&b.speed_kph))
+        {
+            Some(apex) => *apex,
+            None => continue,
+        };
+        let (entry, exit) = match (
+            sample_at(&points, segment.start_m),
+            sample_at(&points, segment.end_m),
+        ) {
+            (Some(entry), Some(exit)) => (entry, exit),
+            _ => continue,
+        };
+
+        let approach: Vec<&LapPoint> = points
+            .iter()
+            .copied()
+            .filter(|point| {
+                point.distance_m >= lookback_start && point.distance_m <= apex.distance_m
+            })
+            .collect();
+        let peak_brake = approach
+            .iter()
+            .fold(0.0f32, |acc, point| acc.max(point.brake));
+        // Braking point is where the last brake application before the apex began.
+        let braking_point_m = approach
+            .iter()
+            .rposition(|point| point.brake >= BRAKE_ON)
+            .map(|last_on| {
+                let mut first_on = last_on;
+                while first_on > 0 && approach[first_on - 1].brake >= BRAKE_ON {
+                    first_on -= 1;
+                }
+                approach[first_on].distance_m
+            });
+
+        stats.push(CornerStats {
+            number: segment.number,
+            braking_point_m,
+            peak_brake,
+            entry_speed_kph: entry.speed_kph,
+            apex_speed_kph: apex.speed_kph,
+            apex_distance_m: apex.distance_m,
+            apex_gear: apex.gear,
+            exit_speed_kph: exit.speed_kph,
+            time_ms: exit.time_ms - entry.time_ms,
+        });
+    }
+    stats
+}
+
+struct Interpolated {
+    speed_kph: f32,
+    time_ms: f32,
+}
+
+fn sample_at(points: &[&LapPoint], distance_m: f32) -> Option<Interpolated> {
+    let idx = points.partition_point(|point| point.distance_m < distance_m);
+    if idx == 0 || idx >= points.len() {
+        return None;
+    }
+    let (a, b) = (points[idx - 1], points[idx]);
+    let span = b.distance_m - a.distance_m;
+    let t = if span > 0.0 {
+        (distance_m - a.distance_m) / span
+    } else {
+        0.0
+    };
+    Some(Interpolated {
+        speed_kph: a.speed_kph + (b.speed_kph - a.speed_kph) * t,
+        time_ms: a.lap_time_ms as f32 + (b.lap_time_ms as f32 - a.lap_time_ms as f32) * t,
+    })
+}
+
+fn straight(number: usize, start: isize, end: isize, step_m: f32) -> TrackSegment {
+    TrackSegment {
+        kind: SegmentKind::Straight,
+        number,
+        start_m: start as f32 * step_m,
+        end_m: end as f32 * step_m,
+        direction: None,
+        heading_change_deg: None,
+        min_radius_m: None,
+    }
+}
+
+fn wrap_angle(angle: f32) -> f32 {
+    let tau = std::f32::consts::TAU;
+    (angle + std::f32::consts::PI).rem_euclid(tau) - std::f32::consts::PI
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two 400 m straights joined by 100 m radius hairpins, starting halfway round one of them.
+    fn stadium() -> Centerline {
+        let radius = 100.0f32;
+        let hairpin = |centre_x: f32, from_deg: f32| {
+            (0..180).map(move |step| {
+                let angle = (from_deg + step as f32).to_radians();
+                (centre_x + radius * angle.cos(), radius * angle.sin())
+            })
+        };
+        let mut points: Vec<(f32, f32)> = hairpin(200.0, -90.0).collect();
+        points.extend((0..200).map(|step| (200.0 - step as f32 * 2.0, radius)));
+        points.extend(hairpin(-200.0, 90.0));
+        points.extend((0..200).map(|step| (-200.0 + step as f32 * 2.0, -radius)));
+        points.rotate_left(90);
+        Centerline::from_points(points).expect("valid centerline")
+    }
+
+    #[test]
+    fn corner_through_the_origin_is_one_corner() {
+        let segments = segment_track(&stadium(), 0.0);
+        let corners: Vec<&TrackSegment> = segments
+            .iter()
+            .filter(|segment| segment.kind == SegmentKind::Corner)
+            .collect();
+
+        assert_eq!(corners.len(), 2);
+        assert!(corners[0].start_m < 0.0 && corners[0].end_m > 0.0);
+        for corner in &corners {
+            assert_eq!(corner.direction, Some(TurnDirection::Left));
+            let heading = corner.heading_change_deg.expect("corner heading");
+            assert!((heading - 180.0).abs() < 10.0, "heading {heading}");
+        }
+    }
+}
@@ -0,0 +1,192 @@
+// Live delta against a reference lap using distance-vs-time traces.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::laps::{LapPoint, LapTrace};
+
+// Beyond this fraction of the reference length the comparison is no longer meaningful.
+const MAX_DISTANCE_OVERRUN: f32 = 1.05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum ReferenceSource {
+    SessionBest,
+    PersonalBest,
+    Lap { lap_id: u64 },
+}
+
+#[derive(Clone, Debug)]
+pub struct ReferenceLap {
+    pub lap_time_ms: i32,
+    pub lap_id: Option<u64>,
+    distance_m: Vec<f32>,
+    time_ms: Vec<f32>,
+}
+
+impl ReferenceLap {
+    pub fn from_points(points: &[LapPoint], lap_time_ms: i32, lap_id: Option<u64>) -> Option<Self> {
+        let mut distance_m = Vec::with_capacity(points.len());
+        let mut time_ms = Vec::with_capacity(points.len());
+        for point in points {
+            if distance_m
+                .last()
+                .is_some_and(|last| point.distance_m <= *last)
+            {
+                continue;
+            }
+            distance_m.push(point.distance_m);
+            time_ms.push(point.lap_time_ms as f32);
+        }
+        if distance_m.len() < 2 {
+            return None;
+        }
+        Some(Self {
+            lap_time_ms,
+            lap_id,
+            distance_m,
+            time_ms,
+        })
+    }
+
+    pub fn length_m(&self) -> f32 {
+        self.distance_m.last().copied().unwrap_or(0.0)
+    }
+
+    pub fn time_at(&self, distance_m: f32) -> Option<f32> {
+        if distance_m < 0.0 || distance_m > self.length_m() * MAX_DISTANCE_OVERRUN {
+            return None;
+        }
+        let idx = self.distance_m.partition_point(|value| *value < distance_m);
+        if idx == 0 {
+            return self.time_ms.first().copied();
+        }
+        if idx >= self.distance_m.len() {
+            // Extrapolate past the end of the trace with the final segment's pace.
+            let n = self.distance_m.len();
+            let (d0, d1) = (self.distance_m[n - 2], self.distance_m[n - 1]);
+            let (t0, t1) = (self.time_ms[n - 2], self.time_ms[n - 1]);
+            return Some(t1 + (distance_m - d1) * (t1 - t0) / (d1 - d0));
+        }
+        let (d0, d1) = (self.distance_m[idx - 1], self.distance_m[idx]);
+        let (t0, t1) = (self.time_ms[idx - 1], self.time_ms[idx]);
+        Some(t0 + (distance_m - d0) / (d1 - d0) * (t1 - t0))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ReferenceKey {
+    session_index: u64,
+    car_id: Option<i32>,
+    track_id: Option<i32>,
+    laps_seen: Option<u64>,
+    personal_best_ms: Option<i32>,
+}
+
+pub struct DeltaTracker {
+    source: ReferenceSource,
+    reference: Option<ReferenceLap>,
+    key: Option<ReferenceKey>,
+}
+
+impl DeltaTracker {
+    pub fn new() -> Self {
+        Self {
+            source: ReferenceSource::SessionBest,
+            reference: None,
+            key: None,
+        }
+    }
+
+    pub fn source(&self) -> ReferenceSource {
+        self.source
+    }
+
+    pub fn reference(&self) -> Option<&ReferenceLap> {
+        self.reference.as_ref()
+    }
+
+    pub fn set_source(&mut self, source: ReferenceSource) {
+        self.source = source;
+        self.key = None;
+        self.reference = None;
+    }
+
+    // Rebuilds the reference only when something that can change it has moved.
+    pub fn refresh(
+        &mut self,
+        lap_history: &VecDeque<Arc<LapTrace>>,
+        personal_best: Option<(i32, &[LapPoint])>,
+        session_index: u64,
+        car_id: Option<i32>,
+        track_id: Option<i32>,
+    ) {
+        let key = ReferenceKey {
+            session_index,
+            car_id,
+            track_id,
+            laps_seen: lap_history.back().map(|lap| lap.id),
+            personal_best_ms: personal_best.map(|(lap_time_ms, _)| lap_time_ms),
+        };
+        if self.key == Some(key) {
+            return;
+        }
+        self.key = Some(key);
+        if let ReferenceSource::Lap { lap_id } = self.source {
+            if self
+                .reference
+                .as_ref()
+                .and_then(|reference| reference.lap_id)
+                == Some(lap_id)
+            {
+                return;
+            }
+        }
+        self.reference = match self.source {
+            ReferenceSource::SessionBest => lap_history
+                .iter()
+                .filter(|lap| {
+                    lap.session_index == session_index
+                        && lap.car_id == car_id
+                        && lap.track_id == track_id
+                        && !lap.partial
+                        && lap.lap_time_ms > 0
+                })
+                .min_by_key(|lap| lap.lap_time_ms)
+                .and_then(|lap| {
+                    ReferenceLap::from_points(&lap.points, lap.lap_time_ms, Some(lap.id))
+                }),
+            ReferenceSource::PersonalBest => personal_best.and_then(|(lap_time_ms, points)| {
+                ReferenceLap::from_points(points, lap_time_ms, None)
+            }),
+            ReferenceSource::Lap { lap_id } => lap_history
+                .iter()
+                .find(|lap| lap.id == lap_id)
+                .and_then(|lap| {
+                    ReferenceLap::from_points(&lap.points, lap.lap_time_ms, Some(lap.id))
+                }),
+        };
+    }
+
+    pub fn compute(&self, distance_m: f32, lap_time_ms: Option<i32>) -> (Option<i32>, Option<i32>) {
+        let (Some(reference), Some(lap_time_ms)) = (self.reference.as_ref(), lap_time_ms) else {
+            return (None, None);
+        };
+        let Some(reference_ms) = reference.time_at(distance_m) else {
+            return (None, None);
+        };
+        let delta_ms = (lap_time_ms as f32 - reference_ms).round() as i32;
+        (
+            Some(delta_ms),
+            Some(reference.lap_time_ms.saturating_add(delta_ms)),
+        )
+    }
+}
+
+impl Default for DeltaTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,146 @@
+// Lap distance estimation from car position with a speed-integration fallback.
+
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::meta::Centerline;
+use crate::model::State;
+
+// Positions further than this from the centerline are not trusted for projection.
+const MAX_CENTERLINE_OFFSET_M: f32 = 50.0;
+// Shortly after the line a small negative distance wraps to a full lap; clamp it instead.
+const LAP_START_GRACE_MS: i32 = 5_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceSource {
+    Centerline,
+    Integrated,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LapDistance {
+    pub distance_m: f32,
+    pub progress: Option<f32>,
+    pub source: DistanceSource,
+}
+
+pub struct LapDistanceEstimator {
+    track_id: Option<i32>,
+    centerline: Option<Arc<Centerline>>,
+    track_length_m: Option<f32>,
+    segment_hint: Option<usize>,
+    // Centerline distance of the start line; unknown until a projection follows a reset or track change.
+    origin_m: Option<f32>,
+    integrated_m: f64,
+    last_lap_time_ms: Option<i32>,
+    last_lap_length_m: Option<f32>,
+}
+
+impl LapDistanceEstimator {
+    pub fn new() -> Self {
+        Self {
+            track_id: None,
+            centerline: None,
+            track_length_m: None,
+            segment_hint: None,
+            origin_m: None,
+            integrated_m: 0.0,
+            last_lap_time_ms: None,
+            last_lap_length_m: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn track_id(&self) -> Option<i32> {
+        self.track_id
+    }
+
+    pub fn set_track(
+        &mut self,
+        track_id: Option<i32>,
+        centerline: Option<Arc<Centerline>>,
+        track_length_m: Option<f32>,
+    ) {
+        self.track_id = track_id;
+        self.track_length_m = track_length_m;
+        self.set_centerline(centerline);
+    }
+
+    // Centerlines load outside the store lock, so they can arrive after the track is set.
+    pub fn set_centerline(&mut self, centerline: Option<Arc<Centerline>>) {
+        self.centerline = centerline;
+        self.segment_hint = None;
+        self.origin_m = None;
+    }
+
+    pub fn update(&mut self, state: &State, lap_started: bool) -> Option<LapDistance> {
+        let lap_time_ms = state.current_lap_time_ms?;
+        let projection = match (self.centerline.as_ref(), state.pos_x, state.pos_z) {
+            (Some(centerline), Some(x), Some(z)) => centerline
+                .project(x, z, self.segment_hint)
+                .filter(|projection| projection.offset_m <= MAX_CENTERLINE_OFFSET_M),
+            _ => None,
+        };
+        if let Some(projection) = projection {
+            self.segment_hint = Some(projection.segment);
+        }
+
+        if lap_started {
+            if self.integrated_m > 0.0 {
+                self.last_lap_length_m = Some(self.integrated_m as f32);
+            }
+            self.integrated_m = 0.0;
+            self.last_lap_time_ms = None;
+            if let Some(projection) = projection {
+                self.origin_m = Some(projection.distance_m);
+            }
+        }
+
+        if let Some(prev) = self.last_lap_time_ms {
+            let dt_ms = lap_time_ms.saturating_sub(prev).max(0);
+            let speed_ms = state.speed_kph.unwrap_or(0.0) as f64 / 3.6;
+            self.integrated_m += speed_ms * dt_ms as f64 / 1000.0;
+        }
+        self.last_lap_time_ms = Some(lap_time_ms);
+
+        if let (Some(centerline), Some(projection)) = (self.centerline.as_ref(), projection) {
+            let length_m = centerline.length_m();
+            // Back-dated by the distance driven since the line, which still holds mid-lap.
+            let origin_m = *self.origin_m.get_or_insert_with(|| {
+                (projection.distance_m - self.integrated_m as f32).rem_euclid(length_m)
+            });
+            let mut distance_m = (projection.distance_m - origin_m).rem_euclid(length_m);
+            if lap_time_ms < LAP_START_GRACE_MS && distance_m > length_m * 0.5 {
+                distance_m = 0.0;
+            }
+            return Some(LapDistance {
+                distance_m,
+                progress: Some(distance_m / length_m),
+                source: DistanceSource::Centerline,
+            });
+        }
+
+        let distance_m = self.integrated_m as f32;
+        let progress = self
+            .track_length_m
+            .or(self.last_lap_length_m)
+            .filter(|length_m| *length_m > 0.0)
+            .map(|length_m| (distance_m / length_m).min(1.0));
+        Some(LapDistance {
+            distance_m,
+            progress,
+            source: DistanceSource::Integrated,
+        })
+    }
+}
+
+impl Default for LapDistanceEstimator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,154 @@
+// Full-throttle and coast-down acceleration samples for the per-car virtual dyno.
+
+use crate::model::State;
+
+const MAX_GEARS: i8 = 8;
+const FULL_THROTTLE: f32 = 0.98;
+const IDLE_PEDAL: f32 = 0.01;
+const MIN_POWER_SPEED_KPH: f32 = 20.0;
+const MIN_COAST_SPEED_KPH: f32 = 30.0;
+// Any tyre outside this slip band is spinning or sliding, so acceleration is not engine-limited.
+const MIN_SLIP_RATIO: f32 = 0.92;
+const MAX_SLIP_RATIO: f32 = 1.08;
+const MIN_CLUTCH_ENGAGED: f32 = 0.99;
+const MAX_CLUTCH_COASTING: f32 = 0.1;
+const ACCEL_WINDOW_MS: i32 = 100;
+const MAX_FRAME_GAP_MS: i32 = 250;
+
+#[derive(Clone, Copy, Debug)]
+pub enum DynoSample {
+    // Full throttle in gear: acceleration still has to be corrected for drag.
+    Power {
+        rpm: f32,
+        speed_mps: f32,
+        accel_mps2: f32,
+    },
+    // Rolling with the drivetrain disconnected: deceleration is drag and rolling resistance only.
+    CoastDown {
+        speed_mps: f32,
+        decel_mps2: f32,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Power,
+    CoastDown,
+}
+
+struct Anchor {
+    mode: Mode,
+    lap_time_ms: i32,
+    speed_kph: f32,
+}
+
+pub struct DynoSampler {
+    anchor: Option<Anchor>,
+}
+
+impl DynoSampler {
+    pub fn new() -> Self {
+        Self { anchor: None }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn update(&mut self, state: &State) -> Option<DynoSample> {
+        let (Some(lap_time_ms), Some(speed_kph)) = (state.current_lap_time_ms, state.speed_kph)
+        else {
+            self.anchor = None;
+            return None;
+        };
+        let Some(mode) = classify(state, speed_kph) else {
+            self.anchor = None;
+            return None;
+        };
+
+        let anchor = match self.anchor.take() {
+            Some(anchor) if anchor.mode == mode => anchor,
+            _ => {
+                self.anchor = Some(Anchor {
+                    mode,
+                    lap_time_ms,
+                    speed_kph,
+                });
+                return None;
+            }
+        };
+        let dt_ms = lap_time_ms - anchor.lap_time_ms;
+        if dt_ms <= 0 || dt_ms > MAX_FRAME_GAP_MS + ACCEL_WINDOW_MS {
+            self.anchor = Some(Anchor {
+                mode,
+                lap_time_ms,
+                speed_kph,
+            });
+            return None;
+        }
+        if dt_ms < ACCEL_WINDOW_MS {
+            self.anchor = Some(anchor);
+            return None;
+        }
+        self.anchor = Some(Anchor {
+            mode,
+            lap_time_ms,
+            speed_kph,
+        });
+
+        let accel_mps2 = (speed_kph - anchor.speed_kph) / 3.6 / (dt_ms as f32 / 1000.0);
+        let speed_mps = (speed_kph + anchor.speed_kph) / 2.0 / 3.6;
+        match mode {
+            Mode::Power => Some(DynoSample::Power {
+                rpm: state.rpm?,
+                speed_mps,
+                accel_mps2,
+            }),
+            Mode::CoastDown => (accel_mps2 < 0.0).then_some(DynoSample::CoastDown {
+                speed_mps,
+                decel_mps2: -accel_mps2,
+            }),
+        }
+    }
+}
+
+impl Default for DynoSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn classify(state: &State, speed_kph: f32) -> Option<Mode> {
+    let throttle = state.throttle.unwrap_or(0.0);
+    let brake = state.brake.unwrap_or(0.0);
+    let gear = state.gear.unwrap_or(0);
+    let slips = [
+        state.tyre_slip_ratio_fl,
+        state.tyre_slip_ratio_fr,
+        state.tyre_slip_ratio_rl,
+        state.tyre_slip_ratio_rr,
+    ];
+    let gripping = slips
+        .iter()
+        .all(|slip| slip.is_none_or(|slip| (MIN_SLIP_RATIO..=MAX_SLIP_RATIO).contains(&slip)));
+    if !gripping || brake > IDLE_PEDAL {
+        return None;
+    }
+
+    let clutch_engaged = state.clutch_engaged.unwrap_or(1.0);
+    if throttle >= FULL_THROTTLE
+        && (1..=MAX_GEARS).contains(&gear)
+        && clutch_engaged >= MIN_CLUTCH_ENGAGED
+        && speed_kph >= MIN_POWER_SPEED_KPH
+        && state.rpm.is_some()
+    {
+        return Some(Mode::Power);
+    }
+    if throttle <= IDLE_PEDAL
+        && (gear == 0 || clutch_engaged <= MAX_CLUTCH_COASTING)
+        && speed_kph >= MIN_COAST_SPEED_KPH
+    {
+        return Some(Mode::CoastDown);
+    }
+    None
+}
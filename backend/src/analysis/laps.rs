@@ -0,0 +1,186 @@
+// Per-lap trace recording.
+// Invariants: points are only captured while IN_RACE; lap time excludes pauses.
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::LAP_TRACE_STRIDE_MS;
+use crate::model::State;
+use telemetry_core::session::LapCompletion;
+
+// A lap whose trace covers less of the completed lap time than this is treated as partial.
+// The local lap timer restarts on entering a race, so a lap joined mid-way starts near 0 ms.
+const PARTIAL_LAP_MISSING_MS: i32 = 1_500;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LapPoint {
+    pub lap_time_ms: u32,
+    pub distance_m: f32,
+    pub speed_kph: f32,
+    pub throttle: f32,
+    pub brake: f32,
+    pub rpm: f32,
+    pub gear: i8,
+    pub yaw_rate: f32,
+    pub pos_x: f32,
+    pub pos_z: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LapTrace {
+    pub id: u64,
+    pub session_index: u64,
+    pub lap_number: i16,
+    pub lap_time_ms: i32,
+    pub car_id: Option<i32>,
+    pub track_id: Option<i32>,
+    pub completed_at_ms: u64,
+    pub partial: bool,
+    // The lap time excludes pauses, but a paused lap is still not representative.
+    pub paused: bool,
+    pub fuel_used_l: Option<f32>,
+    pub distance_m: f32,
+    pub points: Vec<LapPoint>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LapSummary {
+    pub id: String,
+    pub lap_number: i16,
+    pub lap_time_ms: i32,
+    pub session_index: u64,
+    pub car_id: Option<i32>,
+    pub track_id: Option<i32>,
+    pub partial: bool,
+    pub distance_m: f32,
+    pub points: usize,
+}
+
+impl LapTrace {
+    pub fn summary(&self, id: String) -> LapSummary {
+        LapSummary {
+            id,
+            lap_number: self.lap_number,
+            lap_time_ms: self.lap_time_ms,
+            session_index: self.session_index,
+            car_id: self.car_id,
+            track_id: self.track_id,
+            partial: self.partial,
+            distance_m: self.distance_m,
+            points: self.points.len(),
+        }
+    }
+}
+
+pub struct LapRecorder {
+    points: Vec<LapPoint>,
+    last_point_ms: Option<u32>,
+    paused: bool,
+    fuel_start_l: Option<f32>,
+    fuel_last_l: Option<f32>,
+    next_id: u64,
+}
+
+impl LapRecorder {
+    pub fn new() -> Self {
+        Self {
+            points: Vec::new(),
+            last_point_ms: None,
+            paused: false,
+            fuel_start_l: None,
+            fuel_last_l: None,
+            next_id: 1,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.points.clear();
+        self.last_point_ms = None;
+        self.paused = false;
+        self.fuel_start_l = None;
+        self.fuel_last_l = None;
+    }
+
+    pub fn mark_paused(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn points(&self) -> &[LapPoint] {
+        &self.points
+    }
+
+    pub fn push(&mut self, state: &State) {
+        if let Some(fuel_l) = state.fuel_l {
+            self.fuel_start_l.get_or_insert(fuel_l);
+            self.fuel_last_l = Some(fuel_l);
+        }
+        let lap_time_ms = match state.current_lap_time_ms {
+            Some(value) if value >= 0 => value as u32,
+            _ => return,
+        };
+        let emit = match self.last_point_ms {
+            Some(prev) => lap_time_ms.saturating_sub(prev) as u64 >= LAP_TRACE_STRIDE_MS,
+            None => true,
+        };
+        if !emit {
+            return;
+        }
+        self.last_point_ms = Some(lap_time_ms);
+        self.points.push(LapPoint {
+            lap_time_ms,
+            distance_m: state.lap_distance_m.unwrap_or(0.0),
+            speed_kph: state.speed_kph.unwrap_or(0.0),
+            throttle: state.throttle.unwrap_or(0.0),
+            brake: state.brake.unwrap_or(0.0),
+            rpm: state.rpm.unwrap_or(0.0),
+            gear: state.gear.unwrap_or(0),
+            yaw_rate: state.yaw_rate.unwrap_or(0.0),
+            pos_x: state.pos_x.unwrap_or(0.0),
+            pos_z: state.pos_z.unwrap_or(0.0),
+        });
+    }
+
+    pub fn finish(
+        &mut self,
+        completion: LapCompletion,
+        session_index: u64,
+        car_id: Option<i32>,
+        track_id: Option<i32>,
+        completed_at_ms: u64,
+    ) -> LapTrace {
+        let points = std::mem::take(&mut self.points);
+        // A refuel during the lap makes the difference meaningless.
+        let fuel_used_l = match (self.fuel_start_l, self.fuel_last_l) {
+            (Some(start), Some(last)) if start >= last => Some(start - last),
+            _ => None,
+        };
+        let covered_ms = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => last.lap_time_ms.saturating_sub(first.lap_time_ms) as i32,
+            _ => 0,
+        };
+        let partial = completion.lap_time_ms - covered_ms > PARTIAL_LAP_MISSING_MS;
+        let distance_m = points.last().map(|point| point.distance_m).unwrap_or(0.0);
+        let trace = LapTrace {
+            id: self.next_id,
+            session_index,
+            lap_number: completion.lap_number,
+            lap_time_ms: completion.lap_time_ms,
+            car_id,
+            track_id,
+            completed_at_ms,
+            partial,
+            paused: self.paused,
+            fuel_used_l,
+            distance_m,
+            points,
+        };
+        self.next_id += 1;
+        self.reset();
+        trace
+    }
+}
+
+impl Default for LapRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,51 @@
+// Lap analysis built on top of the session tracker.
+
+mod braking;
+mod compare;
+mod corners;
+mod delta;
+mod distance;
+mod dyno;
+mod laps;
+mod performance;
+mod replay;
+mod report;
+mod sectors;
+mod shifts;
+mod suspension;
+mod tracker;
+mod transmission;
+mod tyre_temps;
+mod tyres;
+
+pub use braking::{BrakingAnalyzer, BrakingEvent, BrakingZone, LapBraking};
+pub use compare::{compare_laps, LapComparison, LapRef};
+pub use corners::{
+    corner_stats, lap_origin_m, segment_track, CornerStats, SegmentKind, TrackSegment,
+    TurnDirection,
+};
+pub use delta::{DeltaTracker, ReferenceLap, ReferenceSource};
+pub use distance::{DistanceSource, LapDistance, LapDistanceEstimator};
+pub use dyno::{DynoSample, DynoSampler};
+pub use laps::{LapPoint, LapRecorder, LapSummary, LapTrace};
+pub use performance::{PerformanceKind, PerformanceResult, PerformanceTester};
+pub use replay::{decode_recording_laps, recording_path};
+pub use report::{
+    FuelStats, LapExclusion, LapTimeStats, LockupStats, ReportLap, SectorExtremes, SessionReport,
+    ShiftAccuracy, TyrePeak,
+};
+pub use sectors::{LapSectors, SectorLayout, SectorTimer};
+pub use shifts::{GearShiftSummary, ShiftAnalyzer, ShiftEvent, ShiftKind, TorquePoint};
+pub use suspension::{
+    BottomingEvent, CornerSuspension, DamperStats, Histogram, HistogramBin, LapSuspension,
+    SuspensionAnalyzer, SuspensionSummary,
+};
+pub use tracker::{LapTracker, LapUpdate};
+pub use transmission::{GearSpec, TransmissionModel, TransmissionSnapshot};
+pub use tyre_temps::{
+    LapTyreTemperatures, TyreTemperatureAlert, TyreTemperatureConfig, TyreTemperatureMonitor,
+    TyreTemperatureStats,
+};
+pub use tyres::{
+    LapTyreEvents, TyreEvent, TyreEventCounts, TyreEventDetector, TyreEventKind, Wheel,
+};
@@ -0,0 +1,264 @@
+// Acceleration and braking test runs timed from packet ids.
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::State;
+
+// GT7 sends one packet per frame at 60 Hz.
+const PACKETS_PER_SECOND: f64 = 60.0;
+const STANDSTILL_KPH: f32 = 0.5;
+const QUARTER_MILE_M: f64 = 402.336;
+const LAUNCH_TARGETS_KPH: [(PerformanceKind, f32); 2] = [
+    (PerformanceKind::ZeroTo100, 100.0),
+    (PerformanceKind::ZeroTo200, 200.0),
+];
+const BRAKE_START_KPH: f32 = 100.0;
+const HARD_BRAKE: f32 = 0.5;
+const BRAKE_RELEASED: f32 = 0.2;
+const BRAKE_MAX_THROTTLE: f32 = 0.1;
+const BRAKE_MAX_SPEED_GAIN_KPH: f32 = 1.0;
+// A launch is abandoned once the driver lifts enough to lose this much speed.
+const LAUNCH_MAX_SPEED_LOSS_KPH: f32 = 5.0;
+const LAUNCH_MAX_BRAKE: f32 = 0.1;
+const LAUNCH_TIMEOUT_S: f64 = 60.0;
+const MAX_PACKET_GAP: i32 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PerformanceKind {
+    #[serde(rename = "0-100")]
+    ZeroTo100,
+    #[serde(rename = "0-200")]
+    ZeroTo200,
+    #[serde(rename = "quarter_mile")]
+    QuarterMile,
+    #[serde(rename = "100-0")]
+    HundredToZero,
+}
+
+impl PerformanceKind {
+    pub const ALL: [PerformanceKind; 4] = [
+        PerformanceKind::ZeroTo100,
+        PerformanceKind::ZeroTo200,
+        PerformanceKind::QuarterMile,
+        PerformanceKind::HundredToZero,
+    ];
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PerformanceResult {
+    pub kind: PerformanceKind,
+    pub time_ms: u32,
+    pub distance_m: f32,
+    // Speed at the end of the run: the trap speed for the quarter mile.
+    pub end_speed_kph: f32,
+    pub lap_number: Option<i16>,
+    pub pos_x: Option<f32>,
+    pub pos_z: Option<f32>,
+}
+
+struct Frame {
+    packet_id: i32,
+    speed_kph: f32,
+}
+
+struct Run {
+    // Fractional packet id at which the run started.
+    start_packet: f64,
+    distance_m: f64,
+    max_speed_kph: f32,
+    lap_number: Option<i16>,
+    pos_x: Option<f32>,
+    pos_z: Option<f32>,
+    done: Vec<PerformanceKind>,
+}
+
+impl Run {
+    fn new(start_packet: f64, state: &State) -> Self {
+        Self {
+            start_packet,
+            distance_m: 0.0,
+            max_speed_kph: 0.0,
+            lap_number: state.current_lap,
+            pos_x: state.pos_x,
+            pos_z: state.pos_z,
+            done: Vec::new(),
+        }
+    }
+
+    fn result(
+        &mut self,
+        kind: PerformanceKind,
+        end_packet: f64,
+        distance_m: f64,
+        end_speed_kph: f32,
+    ) -> PerformanceResult {
+        self.done.push(kind);
+        PerformanceResult {
+            kind,
+            time_ms: ((end_packet - self.start_packet) / PACKETS_PER_SECOND * 1000.0).round()
+                as u32,
+            distance_m: distance_m as f32,
+            end_speed_kph,
+            lap_number: self.lap_number,
+            pos_x: self.pos_x,
+            pos_z: self.pos_z,
+        }
+    }
+}
+
+pub struct PerformanceTester {
+    enabled: bool,
+    last: Option<Frame>,
+    standstill_packet: Option<i32>,
+    launch: Option<Run>,
+    stop: Option<Run>,
+}
+
+impl PerformanceTester {
+    pub fn new() -> Self {
+        Self::with_enabled(false)
+    }
+
+    fn with_enabled(enabled: bool) -> Self {
+        Self {
+            enabled,
+            last: None,
+            standstill_packet: None,
+            launch: None,
+            stop: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::with_enabled(self.enabled);
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        *self = Self::with_enabled(enabled);
+    }
+
+    // Returns results completed on this frame.
+    pub fn update(&mut self, state: &State) -> Vec<PerformanceResult> {
+        let mut results = Vec::new();
+        if !self.enabled {
+            return results;
+        }
+        let (Some(packet_id), Some(speed_kph)) = (state.packet_id, state.speed_kph) else {
+            return results;
+        };
+        let last = match self.last.take() {
+            Some(last)
+                if packet_id > last.packet_id
+                    && packet_id - last.packet_id <= MAX_PACKET_GAP
+                    && !state.is_paused.unwrap_or(false) =>
+            {
+                last
+            }
+            Some(last) if packet_id == last.packet_id => {
+                self.last = Some(last);
+                return results;
+            }
+            _ => {
+                self.launch = None;
+                self.stop = None;
+                self.standstill_packet = None;
+                self.last = Some(Frame {
+                    packet_id,
+                    speed_kph,
+                });
+                return results;
+            }
+        };
+        self.last = Some(Frame {
+            packet_id,
+            speed_kph,
+        });
+
+        let brake = state.brake.unwrap_or(0.0);
+        let throttle = state.throttle.unwrap_or(0.0);
+        let (prev_packet, prev_kph) = (last.packet_id as f64, last.speed_kph);
+        let packets = packet_id as f64 - prev_packet;
+        let step_m = (prev_kph + speed_kph) as f64 / 2.0 / 3.6 * packets / PACKETS_PER_SECOND;
+        // Fraction of this frame at which a target between the two samples was crossed.
+        let crossing = |from: f64, to: f64, target: f64| (target - from) / (to - from);
+
+        if speed_kph < STANDSTILL_KPH {
+            self.standstill_packet = Some(packet_id);
+            self.launch = None;
+        } else if self.launch.is_none() {
+            if let Some(start) = self.standstill_packet.take() {
+                self.launch = Some(Run::new(start as f64, state));
+            }
+        }
+
+        if let Some(run) = self.launch.as_mut() {
+            let prev_distance = run.distance_m;
+            run.distance_m += step_m;
+            run.max_speed_kph = run.max_speed_kph.max(speed_kph);
+            for (kind, target) in LAUNCH_TARGETS_KPH {
+                if !run.done.contains(&kind) && prev_kph < target && speed_kph >= target {
+                    let t = crossing(prev_kph as f64, speed_kph as f64, target as f64);
+                    let distance = prev_distance + (run.distance_m - prev_distance) * t;
+                    results.push(run.result(kind, prev_packet + packets * t, distance, target));
+                }
+            }
+            if !run.done.contains(&PerformanceKind::QuarterMile)
+                && prev_distance < QUARTER_MILE_M
+                && run.distance_m >= QUARTER_MILE_M
+            {
+                let t = crossing(prev_distance, run.distance_m, QUARTER_MILE_M);
+                let trap_kph = prev_kph + (speed_kph - prev_kph) * t as f32;
+                results.push(run.result(
+                    PerformanceKind::QuarterMile,
+                    prev_packet + packets * t,
+                    QUARTER_MILE_M,
+                    trap_kph,
+                ));
+            }
+            let finished = run.done.len() == LAUNCH_TARGETS_KPH.len() + 1;
+            let aborted = brake > LAUNCH_MAX_BRAKE
+                || speed_kph < run.max_speed_kph - LAUNCH_MAX_SPEED_LOSS_KPH
+                || (packet_id as f64 - run.start_packet) / PACKETS_PER_SECOND > LAUNCH_TIMEOUT_S;
+            if finished || aborted {
+                self.launch = None;
+            }
+        }
+
+        if let Some(run) = self.stop.as_mut() {
+            run.distance_m += step_m;
+            if speed_kph < STANDSTILL_KPH {
+                let distance = run.distance_m;
+                results.push(run.result(
+                    PerformanceKind::HundredToZero,
+                    packet_id as f64,
+                    distance,
+                    0.0,
+                ));
+                self.stop = None;
+            } else if brake < BRAKE_RELEASED
+                || throttle > BRAKE_MAX_THROTTLE
+                || speed_kph > prev_kph + BRAKE_MAX_SPEED_GAIN_KPH
+            {
+                self.stop = None;
+            }
+        } else if prev_kph >= BRAKE_START_KPH && speed_kph < BRAKE_START_KPH && brake >= HARD_BRAKE
+        {
+            let t = crossing(prev_kph as f64, speed_kph as f64, BRAKE_START_KPH as f64);
+            let mut run = Run::new(prev_packet + packets * t, state);
+            run.distance_m = step_m * (1.0 - t);
+            self.stop = Some(run);
+        }
+
+        results
+    }
+}
+
+impl Default for PerformanceTester {
+    fn default() -> Self {
+        Self::new()
+    }
+}
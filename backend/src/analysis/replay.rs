@@ -0,0 +1,106 @@
+// Offline lap extraction from recorded demo packets.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use telemetry_core::session::{SessionState, SessionTracker};
+
+use super::laps::LapTrace;
+use super::tracker::LapTracker;
+use crate::constants::DEMO_DIR;
+use crate::demo::{decode_packet, parse_record_header, resolve_demo_path, RECORD_HEADER_LEN};
+use crate::meta::{MetadataStore, TrackDetector};
+use crate::utils::now_epoch_ms;
+
+const RECORDING_EXTENSION: &str = "bin";
+
+pub fn recording_path(data_dir: &Path, name: &str) -> Option<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-');
+    if !valid {
+        return None;
+    }
+    let default_path = resolve_demo_path(data_dir);
+    if default_path.file_stem().and_then(|stem| stem.to_str()) == Some(name) {
+        return Some(default_path);
+    }
+    Some(
+        data_dir
+            .join(DEMO_DIR)
+            .join(format!("{name}.{RECORDING_EXTENSION}")),
+    )
+}
+
+pub fn decode_recording_laps(path: &Path, meta: &MetadataStore) -> std::io::Result<Vec<LapTrace>> {
+    let data = fs::read(path)?;
+    let mut session = SessionTracker::new();
+    let mut track_detector = TrackDetector::new();
+    let mut tracker = LapTracker::new();
+    let mut laps = Vec::new();
+    let mut offset = 0usize;
+    let mut last_offset_ms = 0u64;
+
+    while offset + RECORD_HEADER_LEN <= data.len() {
+        let header = data[offset..offset + RECORD_HEADER_LEN].try_into().unwrap();
+        let (t_ms, len) = parse_record_header(header);
+        offset += RECORD_HEADER_LEN;
+        if offset + len > data.len() {
+            break;
+        }
+        let encrypted = &data[offset..offset + len];
+        offset += len;
+        last_offset_ms = last_offset_ms.max(t_ms);
+
+        let Some((frame, packet_meta)) = decode_packet(encrypted) else {
+            continue;
+        };
+        let events = session.apply_frame(&frame, t_ms, packet_meta.car_id);
+        if let Some(transition) = events.transition {
+            if transition.to == SessionState::NotInRace
+                || transition.from == SessionState::NotInRace
+            {
+                tracker.reset();
+                track_detector.reset();
+            }
+        }
+        let track_id = track_detector.update(
+            session.session_state == SessionState::InRace,
+            frame.is_paused.unwrap_or(false),
+            frame.current_lap,
+            packet_meta.position_xz,
+            meta.track_bounds(),
+        );
+        session.set_track_id(track_id);
+
+        let update = tracker.update(&mut session, events.lap_completed, meta, t_ms);
+        if let Some(track_id) = update.centerline_needed {
+            tracker
+                .distance
+                .set_centerline(meta.get_track_centerline(track_id));
+        }
+        if let Some(lap) = update.lap {
+            laps.push(lap);
+        }
+    }
+
+    // Offsets are relative to the recording start; the file was last written with the final
+    // packet, so its mtime anchors the offsets to wall-clock time.
+    let started_at_ms = recording_end_epoch_ms(path).saturating_sub(last_offset_ms);
+    for lap in &mut laps {
+        lap.completed_at_ms += started_at_ms;
+    }
+
+    Ok(laps)
+}
+
+fn recording_end_epoch_ms(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_else(now_epoch_ms)
+}
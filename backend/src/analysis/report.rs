@@ -0,0 +1,574 @@
+// Per-session lap consistency and driver performance report, as JSON, Markdown or HTML.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use super::laps::LapTrace;
+use super::shifts::ShiftKind;
+use super::tracker::LapTracker;
+use super::tyre_temps::PitLap;
+use super::tyres::{Wheel, WHEELS};
+use crate::meta::MetadataStore;
+
+// Upshifts this close to the optimal point count as accurate.
+const SHIFT_WINDOW_RPM: f32 = 250.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LapExclusion {
+    // The trace does not cover the whole lap, e.g. a lap joined mid-way.
+    Partial,
+    InLap,
+    OutLap,
+    Paused,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportLap {
+    pub lap_id: u64,
+    pub lap_number: i16,
+    pub lap_time_ms: i32,
+    pub excluded: Option<LapExclusion>,
+    pub sector_ms: Vec<Option<i32>>,
+    pub fuel_used_l: Option<f32>,
+    pub lockups: Option<u32>,
+    pub wheelspins: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LapTimeStats {
+    pub counted_laps: usize,
+    pub excluded_laps: usize,
+    pub best_ms: Option<i32>,
+    pub worst_ms: Option<i32>,
+    pub mean_ms: Option<f32>,
+    pub stddev_ms: Option<f32>,
+    pub spread_ms: Option<i32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SectorExtremes {
+    pub sector: usize,
+    pub best_ms: Option<i32>,
+    pub best_lap: Option<i16>,
+    pub worst_ms: Option<i32>,
+    pub worst_lap: Option<i16>,
+    pub mean_ms: Option<f32>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct FuelStats {
+    pub laps: usize,
+    pub mean_l_per_lap: Option<f32>,
+    pub min_l: Option<f32>,
+    pub max_l: Option<f32>,
+    pub total_l: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TyrePeak {
+    pub wheel: Wheel,
+    pub peak_c: f32,
+    pub lap_number: i16,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LockupStats {
+    pub lockups: u32,
+    pub lockups_per_lap: Option<f32>,
+    pub worst_lap: Option<i16>,
+    pub wheelspins: u32,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ShiftAccuracy {
+    pub upshifts: usize,
+    // Upshifts in gears with a known optimal point.
+    pub rated: usize,
+    pub window_rpm: f32,
+    pub within_window: usize,
+    pub accuracy_pct: Option<f32>,
+    // Positive when shifting later than optimal.
+    pub mean_deviation_rpm: Option<f32>,
+    pub mean_abs_deviation_rpm: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SessionReport {
+    pub session_index: u64,
+    pub car_id: Option<i32>,
+    pub car_name: Option<String>,
+    pub track_id: Option<i32>,
+    pub track_name: Option<String>,
+    // False while the session is still running.
+    pub complete: bool,
+    pub generated_at_ms: u64,
+    pub lap_times: LapTimeStats,
+    pub sectors: Vec<SectorExtremes>,
+    pub fuel: FuelStats,
+    pub tyre_peaks: Vec<TyrePeak>,
+    pub lockups: LockupStats,
+    pub shifts: ShiftAccuracy,
+    pub laps: Vec<ReportLap>,
+}
+
+struct Table {
+    title: &'static str,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl SessionReport {
+    // Lap analyzers only hold the running session, so this must be called before they reset.
+    pub fn build<'a>(
+        session_index: u64,
+        history: impl IntoIterator<Item = &'a LapTrace>,
+        tracker: &LapTracker,
+        meta: &MetadataStore,
+        complete: bool,
+        generated_at_ms: u64,
+    ) -> Self {
+        let traces: Vec<&LapTrace> = history
+            .into_iter()
+            .filter(|lap| lap.session_index == session_index)
+            .collect();
+        let sectors: HashMap<u64, &Vec<Option<i32>>> = tracker
+            .sectors
+            .laps()
+            .iter()
+            .map(|lap| (lap.lap_id, &lap.sector_ms))
+            .collect();
+        let tyre_events: HashMap<u64, (u32, u32)> = tracker
+            .tyres
+            .laps()
+            .iter()
+            .map(|lap| (lap.lap_id, (lap.counts.lockups, lap.counts.wheelspins)))
+            .collect();
+        let pit_laps: HashMap<u64, PitLap> = tracker
+            .tyre_temps
+            .laps()
+            .iter()
+            .filter_map(|lap| lap.pit_lap.map(|pit_lap| (lap.lap_id, pit_lap)))
+            .collect();
+
+        let laps: Vec<ReportLap> = traces
+            .iter()
+            .map(|trace| ReportLap {
+                lap_id: trace.id,
+                lap_number: trace.lap_number,
+                lap_time_ms: trace.lap_time_ms,
+                excluded: match pit_laps.get(&trace.id) {
+                    _ if trace.partial => Some(LapExclusion::Partial),
+                    Some(PitLap::In) => Some(LapExclusion::InLap),
+                    Some(PitLap::Out) => Some(LapExclusion::OutLap),
+                    None if trace.paused => Some(LapExclusion::Paused),
+                    None => None,
+                },
+                sector_ms: sectors
+                    .get(&trace.id)
+                    .map(|sector_ms| sector_ms.to_vec())
+                    .unwrap_or_default(),
+                fuel_used_l: trace.fuel_used_l,
+                lockups: tyre_events.get(&trace.id).map(|counts| counts.0),
+                wheelspins: tyre_events.get(&trace.id).map(|counts| counts.1),
+            })
+            .collect();
+        let counted: Vec<&ReportLap> = laps.iter().filter(|lap| lap.excluded.is_none()).collect();
+
+        let car_id = traces.iter().rev().find_map(|lap| lap.car_id);
+        let track_id = traces.iter().rev().find_map(|lap| lap.track_id);
+        Self {
+            session_index,
+            car_id,
+            car_name: car_id
+                .and_then(|id| meta.get_car_name(id))
+                .map(str::to_string),
+            track_id,
+            track_name: track_id
+                .and_then(|id| meta.get_track_name(id))
+                .map(str::to_string),
+            complete,
+            generated_at_ms,
+            lap_times: lap_time_stats(&counted, laps.len() - counted.len()),
+            sectors: sector_extremes(&counted, tracker.sectors.layout().sector_count()),
+            fuel: fuel_stats(&counted),
+            tyre_peaks: tyre_peaks(tracker, &laps),
+            lockups: lockup_stats(&laps),
+            shifts: shift_accuracy(tracker),
+            laps,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title());
+        for (label, value) in self.summary() {
+            let _ = writeln!(out, "- **{label}:** {value}");
+        }
+        for table in self.tables() {
+            let _ = write!(out, "\n## {}\n\n", table.title);
+            let _ = writeln!(out, "| {} |", table.headers.join(" | "));
+            let _ = writeln!(out, "|{}", " --- |".repeat(table.headers.len()));
+            for row in &table.rows {
+                let _ = writeln!(out, "| {} |", row.join(" | "));
+            }
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let title = escape_html(&self.title());
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n\
+             body{{font-family:system-ui,sans-serif;margin:2rem;color:#222}}\n\
+             table{{border-collapse:collapse;margin-bottom:1.5rem}}\n\
+             th,td{{border:1px solid #ccc;padding:.3rem .6rem;text-align:right}}\n\
+             th{{background:#f2f2f2}}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>\n<ul>\n"
+        );
+        for (label, value) in self.summary() {
+            let _ = writeln!(
+                out,
+                "<li><strong>{}:</strong> {}</li>",
+                escape_html(label),
+                escape_html(&value)
+            );
+        }
+        out.push_str("</ul>\n");
+        for table in self.tables() {
+            let _ = writeln!(out, "<h2>{}</h2>\n<table>\n<tr>", escape_html(table.title));
+            for header in &table.headers {
+                let _ = write!(out, "<th>{}</th>", escape_html(header));
+            }
+            out.push_str("</tr>\n");
+            for row in &table.rows {
+                out.push_str("<tr>");
+                for cell in row {
+                    let _ = write!(out, "<td>{}</td>", escape_html(cell));
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</table>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+
+    fn title(&self) -> String {
+        let car = self
+            .car_name
+            .clone()
+            .or_else(|| self.car_id.map(|id| format!("car {id}")));
+        let track = self
+            .track_name
+            .clone()
+            .or_else(|| self.track_id.map(|id| format!("track {id}")));
+        let mut title = format!("Session {} report", self.session_index);
+        match (car, track) {
+            (Some(car), Some(track)) => title.push_str(&format!(": {car} at {track}")),
+            (Some(name), None) | (None, Some(name)) => title.push_str(&format!(": {name}")),
+            (None, None) => {}
+        }
+        title
+    }
+
+    fn summary(&self) -> Vec<(&'static str, String)> {
+        let stats = &self.lap_times;
+        let shifts = &self.shifts;
+        vec![
+            (
+                "Status",
+                if self.complete {
+                    "complete"
+                } else {
+                    "in progress"
+                }
+                .to_string(),
+            ),
+            (
+                "Laps",
+                format!(
+                    "{} counted, {} excluded",
+                    stats.counted_laps, stats.excluded_laps
+                ),
+            ),
+            ("Best lap", fmt_time(stats.best_ms)),
+            ("Worst lap", fmt_time(stats.worst_ms)),
+            (
+                "Mean lap",
+                fmt_time(stats.mean_ms.map(|ms| ms.round() as i32)),
+            ),
+            ("Standard deviation", fmt_seconds(stats.stddev_ms)),
+            ("Spread", fmt_seconds(stats.spread_ms.map(|ms| ms as f32))),
+            (
+                "Fuel per lap",
+                fmt_value(self.fuel.mean_l_per_lap, |value| format!("{value:.2} L")),
+            ),
+            (
+                "Lockups",
+                format!(
+                    "{} ({} per lap)",
+                    self.lockups.lockups,
+                    fmt_value(self.lockups.lockups_per_lap, |value| format!("{value:.1}"))
+                ),
+            ),
+            (
+                "Shift accuracy",
+                format!(
+                    "{} of {} rated upshifts within {:.0} rpm ({})",
+                    shifts.within_window,
+                    shifts.rated,
+                    shifts.window_rpm,
+                    fmt_value(shifts.accuracy_pct, |value| format!("{value:.0}%"))
+                ),
+            ),
+        ]
+    }
+
+    fn tables(&self) -> Vec<Table> {
+        let sector_count = self.sectors.len();
+        let mut lap_headers = headers(&["Lap", "Time"]);
+        lap_headers.extend((1..=sector_count).map(|sector| format!("S{sector}")));
+        lap_headers.extend(headers(&["Fuel (L)", "Lockups", "Excluded"]));
+        vec![
+            Table {
+                title: "Laps",
+                headers: lap_headers,
+                rows: self
+                    .laps
+                    .iter()
+                    .map(|lap| {
+                        let mut row =
+                            vec![lap.lap_number.to_string(), fmt_time(Some(lap.lap_time_ms))];
+                        row.extend(
+                            (0..sector_count).map(|sector| {
+                                fmt_time(lap.sector_ms.get(sector).copied().flatten())
+                            }),
+                        );
+                        row.push(fmt_value(lap.fuel_used_l, |value| format!("{value:.2}")));
+                        row.push(fmt_value(lap.lockups, |value| value.to_string()));
+                        row.push(match lap.excluded {
+                            Some(LapExclusion::Partial) => "partial".to_string(),
+                            Some(LapExclusion::InLap) => "in-lap".to_string(),
+                            Some(LapExclusion::OutLap) => "out-lap".to_string(),
+                            Some(LapExclusion::Paused) => "paused".to_string(),
+                            None => String::new(),
+                        });
+                        row
+                    })
+                    .collect(),
+            },
+            Table {
+                title: "Sectors",
+                headers: headers(&["Sector", "Best", "Best lap", "Worst", "Worst lap", "Mean"]),
+                rows: self
+                    .sectors
+                    .iter()
+                    .map(|sector| {
+                        vec![
+                            (sector.sector + 1).to_string(),
+                            fmt_time(sector.best_ms),
+                            fmt_value(sector.best_lap, |lap| lap.to_string()),
+                            fmt_time(sector.worst_ms),
+                            fmt_value(sector.worst_lap, |lap| lap.to_string()),
+                            fmt_time(sector.mean_ms.map(|ms| ms.round() as i32)),
+                        ]
+                    })
+                    .collect(),
+            },
+            Table {
+                title: "Tyre temperature peaks",
+                headers: headers(&["Tyre", "Peak (°C)", "Lap"]),
+                rows: self
+                    .tyre_peaks
+                    .iter()
+                    .map(|peak| {
+                        vec![
+                            wheel_label(peak.wheel).to_string(),
+                            format!("{:.1}", peak.peak_c),
+                            peak.lap_number.to_string(),
+                        ]
+                    })
+                    .collect(),
+            },
+        ]
+    }
+}
+
+fn lap_time_stats(counted: &[&ReportLap], excluded_laps: usize) -> LapTimeStats {
+    let times: Vec<i32> = counted.iter().map(|lap| lap.lap_time_ms).collect();
+    let best_ms = times.iter().copied().min();
+    let worst_ms = times.iter().copied().max();
+    let mean_ms = mean(times.iter().map(|ms| *ms as f32));
+    let stddev_ms = match mean_ms {
+        Some(mean_ms) if times.len() >= 2 => {
+            let sum_sq: f32 = times.iter().map(|ms| (*ms as f32 - mean_ms).powi(2)).sum();
+            Some((sum_sq / (times.len() - 1) as f32).sqrt())
+        }
+        _ => None,
+    };
+    LapTimeStats {
+        counted_laps: times.len(),
+        excluded_laps,
+        best_ms,
+        worst_ms,
+        mean_ms,
+        stddev_ms,
+        spread_ms: best_ms.zip(worst_ms).map(|(best, worst)| worst - best),
+    }
+}
+
+fn sector_extremes(counted: &[&ReportLap], sector_count: usize) -> Vec<SectorExtremes> {
+    (0..sector_count)
+        .map(|sector| {
+            let times: Vec<(i32, i16)> = counted
+                .iter()
+                .filter_map(|lap| {
+                    let time = lap.sector_ms.get(sector).copied().flatten()?;
+                    Some((time, lap.lap_number))
+                })
+                .collect();
+            let best = times.iter().min_by_key(|(time, _)| *time);
+            let worst = times.iter().max_by_key(|(time, _)| *time);
+            SectorExtremes {
+                sector,
+                best_ms: best.map(|(time, _)| *time),
+                best_lap: best.map(|(_, lap)| *lap),
+                worst_ms: worst.map(|(time, _)| *time),
+                worst_lap: worst.map(|(_, lap)| *lap),
+                mean_ms: mean(times.iter().map(|(time, _)| *time as f32)),
+            }
+        })
+        .collect()
+}
+
+fn fuel_stats(counted: &[&ReportLap]) -> FuelStats {
+    let used: Vec<f32> = counted.iter().filter_map(|lap| lap.fuel_used_l).collect();
+    FuelStats {
+        laps: used.len(),
+        mean_l_per_lap: mean(used.iter().copied()),
+        min_l: used.iter().copied().reduce(f32::min),
+        max_l: used.iter().copied().reduce(f32::max),
+        total_l: used.iter().sum(),
+    }
+}
+
+fn tyre_peaks(tracker: &LapTracker, laps: &[ReportLap]) -> Vec<TyrePeak> {
+    let mut peaks: [Option<TyrePeak>; 4] = [None, None, None, None];
+    for lap in tracker.tyre_temps.laps() {
+        if !laps.iter().any(|report| report.lap_id == lap.lap_id) {
+            continue;
+        }
+        for (idx, stats) in [lap.fl, lap.fr, lap.rl, lap.rr].iter().enumerate() {
+            let Some(max_c) = stats.max_c else {
+                continue;
+            };
+            if peaks[idx].as_ref().is_none_or(|peak| max_c > peak.peak_c) {
+                peaks[idx] = Some(TyrePeak {
+                    wheel: WHEELS[idx],
+                    peak_c: max_c,
+                    lap_number: lap.lap_number,
+                });
+            }
+        }
+    }
+    peaks.into_iter().flatten().collect()
+}
+
+fn lockup_stats(laps: &[ReportLap]) -> LockupStats {
+    let tracked: Vec<&ReportLap> = laps.iter().filter(|lap| lap.lockups.is_some()).collect();
+    let lockups = tracked.iter().filter_map(|lap| lap.lockups).sum();
+    LockupStats {
+        lockups,
+        lockups_per_lap: (!tracked.is_empty()).then(|| lockups as f32 / tracked.len() as f32),
+        worst_lap: tracked
+            .iter()
+            .filter(|lap| lap.lockups.unwrap_or(0) > 0)
+            .max_by_key(|lap| lap.lockups)
+            .map(|lap| lap.lap_number),
+        wheelspins: tracked.iter().filter_map(|lap| lap.wheelspins).sum(),
+    }
+}
+
+fn shift_accuracy(tracker: &LapTracker) -> ShiftAccuracy {
+    let shifts = &tracker.shifts;
+    let upshifts: Vec<_> = shifts
+        .shifts()
+        .iter()
+        .filter(|shift| shift.kind == ShiftKind::Up)
+        .collect();
+    // Rate against the current optimum so early shifts benefit from the curve learned later.
+    let deviations: Vec<f32> = upshifts
+        .iter()
+        .filter_map(|shift| {
+            shifts
+                .optimal_upshift_rpm(shift.from_gear, &tracker.transmission)
+                .map(|optimal| shift.rpm - optimal)
+        })
+        .collect();
+    let within_window = deviations
+        .iter()
+        .filter(|deviation| deviation.abs() <= SHIFT_WINDOW_RPM)
+        .count();
+    ShiftAccuracy {
+        upshifts: upshifts.len(),
+        rated: deviations.len(),
+        window_rpm: SHIFT_WINDOW_RPM,
+        within_window,
+        accuracy_pct: (!deviations.is_empty())
+            .then(|| within_window as f32 / deviations.len() as f32 * 100.0),
+        mean_deviation_rpm: mean(deviations.iter().copied()),
+        mean_abs_deviation_rpm: mean(deviations.iter().map(|deviation| deviation.abs())),
+    }
+}
+
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| {
+        (sum + value, count + 1)
+    });
+    (count > 0).then(|| sum / count as f32)
+}
+
+fn fmt_value<T>(value: Option<T>, format: impl Fn(T) -> String) -> String {
+    value.map(format).unwrap_or_else(|| "-".to_string())
+}
+
+fn fmt_time(ms: Option<i32>) -> String {
+    fmt_value(ms, |ms| {
+        let ms = ms.max(0);
+        format!("{}:{:02}.{:03}", ms / 60_000, ms / 1_000 % 60, ms % 1_000)
+    })
+}
+
+fn fmt_seconds(ms: Option<f32>) -> String {
+    fmt_value(ms, |ms| format!("{:.3} s", ms / 1_000.0))
+}
+
+fn headers(labels: &[&str]) -> Vec<String> {
+    labels.iter().map(|label| label.to_string()).collect()
+}
+
+fn wheel_label(wheel: Wheel) -> &'static str {
+    match wheel {
+        Wheel::Fl => "Front left",
+        Wheel::Fr => "Front right",
+        Wheel::Rl => "Rear left",
+        Wheel::Rr => "Rear right",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
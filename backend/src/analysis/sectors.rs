@@ -0,0 +1,229 @@
+// Sector splits, best sectors and theoretical best lap for the current session.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::constants::{DEFAULT_SECTOR_COUNT, LAP_HISTORY_CAP};
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SectorLayout {
+    Markers { markers_m: Vec<f32> },
+    Fractions { count: usize },
+}
+
+impl SectorLayout {
+    pub fn sector_count(&self) -> usize {
+        match self {
+            SectorLayout::Markers { markers_m } => markers_m.len() + 1,
+            SectorLayout::Fractions { count } => *count,
+        }
+    }
+
+    fn sector_at(&self, distance_m: f32, progress: Option<f32>) -> Option<usize> {
+        match self {
+            SectorLayout::Markers { markers_m } => Some(
+                markers_m
+                    .iter()
+                    .filter(|marker| distance_m >= **marker)
+                    .count(),
+            ),
+            SectorLayout::Fractions { count } => progress
+                .map(|progress| ((progress * *count as f32).floor() as usize).min(count - 1)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LapSectors {
+    pub lap_id: u64,
+    pub lap_number: i16,
+    pub lap_time_ms: i32,
+    pub sector_ms: Vec<Option<i32>>,
+}
+
+pub struct SectorTimer {
+    layout: SectorLayout,
+    // A different layout that takes over once the lap in progress is finished.
+    pending: Option<SectorLayout>,
+    current: Option<usize>,
+    started_at_line: bool,
+    // Invariants: splits[s] is the lap time at the end of sector s, for s < sector_count - 1.
+    splits: Vec<Option<i32>>,
+    best_ms: Vec<Option<i32>>,
+    last_delta_ms: Option<i32>,
+    laps: VecDeque<LapSectors>,
+}
+
+impl SectorTimer {
+    pub fn new() -> Self {
+        Self::with_layout(SectorLayout::Fractions {
+            count: DEFAULT_SECTOR_COUNT,
+        })
+    }
+
+    fn with_layout(layout: SectorLayout) -> Self {
+        let count = layout.sector_count();
+        Self {
+            layout,
+            pending: None,
+            current: None,
+            started_at_line: false,
+            splits: vec![None; count - 1],
+            best_ms: vec![None; count],
+            last_delta_ms: None,
+            laps: VecDeque::with_capacity(LAP_HISTORY_CAP),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        let layout = self.pending.take().unwrap_or_else(|| self.layout.clone());
+        *self = Self::with_layout(layout);
+    }
+
+    // The same layout keeps the splits and bests. A new one waits for the lap in progress to
+    // finish, since its sectors are not comparable with the old ones.
+    pub fn set_markers(&mut self, markers_m: Option<&[f32]>) {
+        let layout = match markers_m {
+            Some(markers_m) if !markers_m.is_empty() => SectorLayout::Markers {
+                markers_m: markers_m.to_vec(),
+            },
+            _ => SectorLayout::Fractions {
+                count: DEFAULT_SECTOR_COUNT,
+            },
+        };
+        if layout == self.layout {
+            self.pending = None;
+        } else if self.current.is_none() {
+            self.apply_layout(layout);
+        } else {
+            self.pending = Some(layout);
+        }
+    }
+
+    // Earlier laps keep the sector times they were recorded with.
+    fn apply_layout(&mut self, layout: SectorLayout) {
+        let laps = std::mem::take(&mut self.laps);
+        *self = Self::with_layout(layout);
+        self.laps = laps;
+    }
+
+    pub fn layout(&self) -> &SectorLayout {
+        &self.layout
+    }
+
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn last_delta_ms(&self) -> Option<i32> {
+        self.last_delta_ms
+    }
+
+    pub fn best_ms(&self) -> &[Option<i32>] {
+        &self.best_ms
+    }
+
+    pub fn laps(&self) -> &VecDeque<LapSectors> {
+        &self.laps
+    }
+
+    pub fn theoretical_best_ms(&self) -> Option<i32> {
+        self.best_ms.iter().copied().sum()
+    }
+
+    pub fn update(&mut self, distance_m: f32, progress: Option<f32>, lap_time_ms: Option<i32>) {
+        let sector = self.layout.sector_at(distance_m, progress);
+        let (sector, lap_time_ms) = match (sector, lap_time_ms) {
+            (Some(sector), Some(lap_time_ms)) => (sector, lap_time_ms),
+            _ => return,
+        };
+        let current = match self.current {
+            Some(current) => current,
+            None => {
+                self.current = Some(sector);
+                return;
+            }
+        };
+        // Only forward crossings count; going backwards is noise or driving the wrong way.
+        if sector <= current {
+            return;
+        }
+        if sector == current + 1 {
+            self.splits[current] = Some(lap_time_ms);
+            self.last_delta_ms = self.sector_delta(current);
+        }
+        self.current = Some(sector);
+    }
+
+    pub fn finish_lap(&mut self, lap_id: u64, lap_number: i16, lap_time_ms: i32) {
+        let last = self.splits.len();
+        if self.current == Some(last) {
+            self.last_delta_ms = self.sector_delta_with_end(last, lap_time_ms);
+        }
+        let sector_ms: Vec<Option<i32>> = (0..=last)
+            .map(|sector| self.sector_time(sector, lap_time_ms))
+            .collect();
+        for (best, time) in self.best_ms.iter_mut().zip(sector_ms.iter()) {
+            if let Some(time) = time {
+                if best.is_none_or(|best| *time < best) {
+                    *best = Some(*time);
+                }
+            }
+        }
+
+        if self.laps.len() >= LAP_HISTORY_CAP {
+            self.laps.pop_front();
+        }
+        self.laps.push_back(LapSectors {
+            lap_id,
+            lap_number,
+            lap_time_ms,
+            sector_ms,
+        });
+
+        if let Some(layout) = self.pending.take() {
+            self.apply_layout(layout);
+        }
+        self.current = Some(0);
+        self.started_at_line = true;
+        self.splits.iter_mut().for_each(|split| *split = None);
+    }
+
+    fn sector_start(&self, sector: usize) -> Option<i32> {
+        if sector == 0 {
+            self.started_at_line.then_some(0)
+        } else {
+            self.splits[sector - 1]
+        }
+    }
+
+    fn sector_time(&self, sector: usize, lap_time_ms: i32) -> Option<i32> {
+        let end = if sector == self.splits.len() {
+            (self.current == Some(sector)).then_some(lap_time_ms)
+        } else {
+            self.splits[sector]
+        };
+        match (self.sector_start(sector), end) {
+            (Some(start), Some(end)) if end > start => Some(end - start),
+            _ => None,
+        }
+    }
+
+    fn sector_delta(&self, sector: usize) -> Option<i32> {
+        let end = self.splits[sector]?;
+        self.sector_delta_with_end(sector, end)
+    }
+
+    fn sector_delta_with_end(&self, sector: usize, end: i32) -> Option<i32> {
+        let time = end - self.sector_start(sector)?;
+        self.best_ms[sector].map(|best| time - best)
+    }
+}
+
+impl Default for SectorTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,361 @@
+// Shift capture, relative torque curve from full-throttle acceleration and optimal upshift points.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use super::transmission::TransmissionModel;
+use crate::model::State;
+
+const MAX_GEARS: usize = 8;
+const SHIFT_HISTORY_CAP: usize = 500;
+const RPM_BIN: f32 = 250.0;
+const MAX_RPM_BINS: usize = 80;
+const FULL_THROTTLE: f32 = 0.98;
+const MIN_SPEED_KPH: f32 = 20.0;
+// Driven wheels above this slip ratio are spinning and do not reflect engine torque.
+const MAX_SLIP_RATIO: f32 = 1.08;
+// Acceleration is measured over this window to smooth out speed quantisation.
+const ACCEL_WINDOW_MS: i32 = 100;
+const MAX_FRAME_GAP_MS: i32 = 250;
+const RATIO_SMOOTHING: f32 = 0.02;
+const MIN_BIN_SAMPLES: u32 = 5;
+const SCAN_STEP_RPM: f32 = 50.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShiftKind {
+    Up,
+    Down,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ShiftEvent {
+    pub kind: ShiftKind,
+    pub from_gear: i8,
+    pub to_gear: i8,
+    pub rpm: f32,
+    pub speed_kph: f32,
+    pub throttle: f32,
+    pub lap_number: Option<i16>,
+    pub lap_time_ms: Option<i32>,
+    pub distance_m: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub optimal_rpm: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TorquePoint {
+    pub rpm: f32,
+    pub relative_torque: f32,
+    pub samples: u32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GearShiftSummary {
+    pub gear: i8,
+    pub rpm_per_kph: Option<f32>,
+    pub optimal_upshift_rpm: Option<f32>,
+    pub upshifts: usize,
+    pub mean_upshift_rpm: Option<f32>,
+    pub mean_deviation_rpm: Option<f32>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct TorqueBin {
+    sum: f64,
+    count: u32,
+}
+
+struct LastFrame {
+    gear: i8,
+    rpm: f32,
+    speed_kph: f32,
+    throttle: f32,
+    lap_time_ms: Option<i32>,
+    distance_m: Option<f32>,
+}
+
+pub struct ShiftAnalyzer {
+    last: Option<LastFrame>,
+    accel_anchor: Option<(i32, f32)>,
+    // Smoothed rpm / speed per gear, only used until the transmission model knows the gearing.
+    observed_rpm_per_kph: [Option<f32>; MAX_GEARS],
+    torque_bins: Vec<TorqueBin>,
+    rev_limiter_rpm: Option<f32>,
+    rev_warning_rpm: Option<f32>,
+    shifts: VecDeque<ShiftEvent>,
+}
+
+impl ShiftAnalyzer {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            accel_anchor: None,
+            observed_rpm_per_kph: [None; MAX_GEARS],
+            torque_bins: vec![TorqueBin::default(); MAX_RPM_BINS],
+            rev_limiter_rpm: None,
+            rev_warning_rpm: None,
+            shifts: VecDeque::with_capacity(SHIFT_HISTORY_CAP),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn shifts(&self) -> &VecDeque<ShiftEvent> {
+        &self.shifts
+    }
+
+    pub fn rev_limiter_rpm(&self) -> Option<f32> {
+        self.rev_limiter_rpm
+    }
+
+    // Where the game's shift light comes on.
+    pub fn rev_warning_rpm(&self) -> Option<f32> {
+        self.rev_warning_rpm
+    }
+
+    // Gear ratio, final drive and tyre radius when the transmission model has them, otherwise
+    // the ratio observed while driving in that gear.
+    fn rpm_per_kph(&self, gear: i8, transmission: &TransmissionModel) -> Option<f32> {
+        if !is_forward(gear) {
+            return None;
+        }
+        transmission
+            .rpm_per_kph(gear)
+            .or(self.observed_rpm_per_kph[gear as usize - 1])
+    }
+
+    pub fn update(&mut self, state: &State, transmission: &TransmissionModel) {
+        let (gear, rpm, speed_kph) = match (state.gear, state.rpm, state.speed_kph) {
+            (Some(gear), Some(rpm), Some(speed_kph)) => (gear, rpm, speed_kph),
+            _ => return,
+        };
+        let throttle = state.throttle.unwrap_or(0.0);
+        if let Some(limiter) = state.rpm_rev_limiter.filter(|value| *value > 0) {
+            self.rev_limiter_rpm = Some(limiter as f32);
+        }
+        if let Some(warning) = state.rpm_rev_warning.filter(|value| *value > 0) {
+            self.rev_warning_rpm = Some(warning as f32);
+        }
+
+        if let Some(last) = self.last.take() {
+            if is_forward(last.gear) && is_forward(gear) && gear != last.gear {
+                self.record_shift(&last, gear, state.current_lap, transmission);
+                self.accel_anchor = None;
+            }
+        }
+
+        let gripping = [state.tyre_slip_ratio_rl, state.tyre_slip_ratio_rr]
+            .iter()
+            .all(|slip| slip.is_none_or(|slip| slip <= MAX_SLIP_RATIO));
+        if is_forward(gear) && speed_kph >= MIN_SPEED_KPH && gripping {
+            let idx = gear as usize - 1;
+            let ratio = rpm / speed_kph;
+            self.observed_rpm_per_kph[idx] = Some(match self.observed_rpm_per_kph[idx] {
+                Some(prev) => prev + (ratio - prev) * RATIO_SMOOTHING,
+                None => ratio,
+            });
+            if throttle >= FULL_THROTTLE {
+                let ratio = self.rpm_per_kph(gear, transmission);
+                self.sample_torque(state.current_lap_time_ms, rpm, speed_kph, ratio);
+            } else {
+                self.accel_anchor = None;
+            }
+        } else {
+            self.accel_anchor = None;
+        }
+
+        self.last = Some(LastFrame {
+            gear,
+            rpm,
+            speed_kph,
+            throttle,
+            lap_time_ms: state.current_lap_time_ms,
+            distance_m: state.lap_distance_m,
+        });
+    }
+
+    fn sample_torque(
+        &mut self,
+        lap_time_ms: Option<i32>,
+        rpm: f32,
+        speed_kph: f32,
+        ratio: Option<f32>,
+    ) {
+        let Some(lap_time_ms) = lap_time_ms else {
+            return;
+        };
+        let (anchor_ms, anchor_kph) = match self.accel_anchor {
+            Some(anchor) => anchor,
+            None => {
+                self.accel_anchor = Some((lap_time_ms, speed_kph));
+                return;
+            }
+        };
+        let dt_ms = lap_time_ms - anchor_ms;
+        if dt_ms <= 0 || dt_ms > MAX_FRAME_GAP_MS + ACCEL_WINDOW_MS {
+            self.accel_anchor = Some((lap_time_ms, speed_kph));
+            return;
+        }
+        if dt_ms < ACCEL_WINDOW_MS {
+            return;
+        }
+        self.accel_anchor = Some((lap_time_ms, speed_kph));
+
+        let Some(ratio) = ratio else {
+            return;
+        };
+        let accel_ms2 = (speed_kph - anchor_kph) / 3.6 / (dt_ms as f32 / 1000.0);
+        // Wheel force is torque times overall ratio, so torque is proportional to accel / ratio.
+        let torque = accel_ms2 / ratio;
+        let bin = (rpm / RPM_BIN) as usize;
+        if let Some(bin) = self.torque_bins.get_mut(bin) {
+            bin.sum += torque as f64;
+            bin.count += 1;
+        }
+    }
+
+    fn record_shift(
+        &mut self,
+        last: &LastFrame,
+        gear: i8,
+        lap_number: Option<i16>,
+        transmission: &TransmissionModel,
+    ) {
+        let kind = if gear > last.gear {
+            ShiftKind::Up
+        } else {
+            ShiftKind::Down
+        };
+        let optimal_rpm = match kind {
+            ShiftKind::Up => self.optimal_upshift_rpm(last.gear, transmission),
+            ShiftKind::Down => None,
+        };
+        if self.shifts.len() >= SHIFT_HISTORY_CAP {
+            self.shifts.pop_front();
+        }
+        self.shifts.push_back(ShiftEvent {
+            kind,
+            from_gear: last.gear,
+            to_gear: gear,
+            rpm: last.rpm,
+            speed_kph: last.speed_kph,
+            throttle: last.throttle,
+            lap_number,
+            lap_time_ms: last.lap_time_ms,
+            distance_m: last.distance_m,
+            optimal_rpm,
+        });
+    }
+
+    pub fn torque_curve(&self) -> Vec<TorquePoint> {
+        let peak = (0..self.torque_bins.len())
+            .filter_map(|idx| self.bin_torque(idx))
+            .fold(0.0f32, f32::max);
+        if peak <= 0.0 {
+            return Vec::new();
+        }
+        (0..self.torque_bins.len())
+            .filter_map(|idx| {
+                self.bin_torque(idx).map(|torque| TorquePoint {
+                    rpm: (idx as f32 + 0.5) * RPM_BIN,
+                    relative_torque: torque / peak,
+                    samples: self.torque_bins[idx].count,
+                })
+            })
+            .collect()
+    }
+
+    // Upshift where the next gear starts giving more wheel force at the same road speed. Until
+    // the torque curve covers both gears this is the game's shift light.
+    pub fn optimal_upshift_rpm(&self, gear: i8, transmission: &TransmissionModel) -> Option<f32> {
+        if !is_forward(gear) || gear as usize >= MAX_GEARS {
+            return None;
+        }
+        self.curve_upshift_rpm(gear, transmission)
+            .or(self.rev_warning_rpm)
+    }
+
+    fn curve_upshift_rpm(&self, gear: i8, transmission: &TransmissionModel) -> Option<f32> {
+        let ratio = self.rpm_per_kph(gear, transmission)?;
+        let next_ratio = self.rpm_per_kph(gear + 1, transmission)?;
+        let curve = self.torque_curve();
+        let (first, last) = (curve.first()?.rpm, curve.last()?.rpm);
+        let limit = self.rev_limiter_rpm.unwrap_or(last).min(last);
+
+        let mut rpm = first;
+        let mut compared = false;
+        while rpm <= limit {
+            let next_rpm = rpm / ratio * next_ratio;
+            if let (Some(current), Some(next)) =
+                (torque_at(&curve, rpm), torque_at(&curve, next_rpm))
+            {
+                if next * next_ratio >= current * ratio {
+                    return Some(rpm);
+                }
+                compared = true;
+            }
+            rpm += SCAN_STEP_RPM;
+        }
+        compared.then_some(limit)
+    }
+
+    pub fn gear_summaries(&self, transmission: &TransmissionModel) -> Vec<GearShiftSummary> {
+        (1..=MAX_GEARS as i8)
+            .filter(|gear| self.rpm_per_kph(*gear, transmission).is_some())
+            .map(|gear| {
+                let upshifts: Vec<&ShiftEvent> = self
+                    .shifts
+                    .iter()
+                    .filter(|shift| shift.kind == ShiftKind::Up && shift.from_gear == gear)
+                    .collect();
+                let optimal = self.optimal_upshift_rpm(gear, transmission);
+                let mean = |values: Vec<f32>| {
+                    (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+                };
+                GearShiftSummary {
+                    gear,
+                    rpm_per_kph: self.rpm_per_kph(gear, transmission),
+                    optimal_upshift_rpm: optimal,
+                    upshifts: upshifts.len(),
+                    mean_upshift_rpm: mean(upshifts.iter().map(|shift| shift.rpm).collect()),
+                    mean_deviation_rpm: optimal.and_then(|optimal| {
+                        mean(upshifts.iter().map(|shift| shift.rpm - optimal).collect())
+                    }),
+                }
+            })
+            .collect()
+    }
+
+    fn bin_torque(&self, idx: usize) -> Option<f32> {
+        let bin = self.torque_bins[idx];
+        (bin.count >= MIN_BIN_SAMPLES).then(|| (bin.sum / bin.count as f64) as f32)
+    }
+}
+
+impl Default for ShiftAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_forward(gear: i8) -> bool {
+    (1..=MAX_GEARS as i8).contains(&gear)
+}
+
+fn torque_at(curve: &[TorquePoint], rpm: f32) -> Option<f32> {
+    let idx = curve.partition_point(|point| point.rpm < rpm);
+    if idx == 0 || idx >= curve.len() {
+        return None;
+    }
+    let (a, b) = (&curve[idx - 1], &curve[idx]);
+    // Do not interpolate across holes in the measured curve.
+    if b.rpm - a.rpm > RPM_BIN * 1.5 {
+        return None;
+    }
+    let t = (rpm - a.rpm) / (b.rpm - a.rpm);
+    Some(a.relative_torque + (b.relative_torque - a.relative_torque) * t)
+}
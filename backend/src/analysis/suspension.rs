@@ -0,0 +1,404 @@
+// Suspension travel and velocity histograms, pitch/roll and bottoming detection.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use serde::Serialize;
+
+use super::tyres::{Wheel, WHEELS};
+use crate::constants::LAP_HISTORY_CAP;
+use crate::model::State;
+
+const PACKETS_PER_SECOND: f32 = 60.0;
+const MAX_PACKET_GAP: i32 = 15;
+const TRAVEL_BIN_MM: f32 = 2.0;
+const VELOCITY_BIN_MM_S: f32 = 10.0;
+const ATTITUDE_BIN_MM: f32 = 2.0;
+// Ride height hysteresis for a bottoming event.
+const BOTTOMING_ENTER_MM: f32 = 10.0;
+const BOTTOMING_EXIT_MM: f32 = 15.0;
+const BOTTOMING_HISTORY_CAP: usize = 500;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HistogramBin {
+    pub from: f32,
+    pub to: f32,
+    pub samples: u32,
+    pub fraction: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Histogram {
+    pub bin_width: f32,
+    pub samples: u32,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub mean: Option<f32>,
+    pub bins: Vec<HistogramBin>,
+}
+
+#[derive(Clone)]
+struct HistogramAccumulator {
+    bin_width: f32,
+    bins: BTreeMap<i32, u32>,
+    samples: u32,
+    sum: f64,
+    min: Option<f32>,
+    max: Option<f32>,
+}
+
+impl HistogramAccumulator {
+    fn new(bin_width: f32) -> Self {
+        Self {
+            bin_width,
+            bins: BTreeMap::new(),
+            samples: 0,
+            sum: 0.0,
+            min: None,
+            max: None,
+        }
+    }
+
+    fn push(&mut self, value: f32) {
+        *self
+            .bins
+            .entry((value / self.bin_width).floor() as i32)
+            .or_insert(0) += 1;
+        self.samples += 1;
+        self.sum += value as f64;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    fn snapshot(&self) -> Histogram {
+        let total = self.samples.max(1) as f32;
+        Histogram {
+            bin_width: self.bin_width,
+            samples: self.samples,
+            min: self.min,
+            max: self.max,
+            mean: (self.samples > 0).then(|| (self.sum / self.samples as f64) as f32),
+            bins: self
+                .bins
+                .iter()
+                .map(|(idx, samples)| HistogramBin {
+                    from: *idx as f32 * self.bin_width,
+                    to: (*idx + 1) as f32 * self.bin_width,
+                    samples: *samples,
+                    fraction: *samples as f32 / total,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DamperStats {
+    // Bump is compression: the suspension height shrinking.
+    pub bump_fraction: Option<f32>,
+    pub mean_bump_mm_s: Option<f32>,
+    pub mean_rebound_mm_s: Option<f32>,
+    pub max_bump_mm_s: Option<f32>,
+    pub max_rebound_mm_s: Option<f32>,
+}
+
+#[derive(Clone, Default)]
+struct DamperAccumulator {
+    bump_samples: u32,
+    rebound_samples: u32,
+    bump_sum: f64,
+    rebound_sum: f64,
+    max_bump: Option<f32>,
+    max_rebound: Option<f32>,
+}
+
+impl DamperAccumulator {
+    // Velocity is positive in bump.
+    fn push(&mut self, velocity_mm_s: f32) {
+        if velocity_mm_s > 0.0 {
+            self.bump_samples += 1;
+            self.bump_sum += velocity_mm_s as f64;
+            self.max_bump = Some(
+                self.max_bump
+                    .map_or(velocity_mm_s, |max| max.max(velocity_mm_s)),
+            );
+        } else if velocity_mm_s < 0.0 {
+            let rebound = -velocity_mm_s;
+            self.rebound_samples += 1;
+            self.rebound_sum += rebound as f64;
+            self.max_rebound = Some(self.max_rebound.map_or(rebound, |max| max.max(rebound)));
+        }
+    }
+
+    fn stats(&self) -> DamperStats {
+        let moving = self.bump_samples + self.rebound_samples;
+        let mean = |sum: f64, samples: u32| (samples > 0).then(|| (sum / samples as f64) as f32);
+        DamperStats {
+            bump_fraction: (moving > 0).then(|| self.bump_samples as f32 / moving as f32),
+            mean_bump_mm_s: mean(self.bump_sum, self.bump_samples),
+            mean_rebound_mm_s: mean(self.rebound_sum, self.rebound_samples),
+            max_bump_mm_s: self.max_bump,
+            max_rebound_mm_s: self.max_rebound,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CornerSuspension {
+    pub wheel: Wheel,
+    pub travel_mm: Histogram,
+    // Positive in bump, negative in rebound.
+    pub velocity_mm_s: Histogram,
+    pub damper: DamperStats,
+}
+
+#[derive(Clone)]
+struct CornerAccumulator {
+    travel: HistogramAccumulator,
+    velocity: HistogramAccumulator,
+    damper: DamperAccumulator,
+}
+
+impl CornerAccumulator {
+    fn new() -> Self {
+        Self {
+            travel: HistogramAccumulator::new(TRAVEL_BIN_MM),
+            velocity: HistogramAccumulator::new(VELOCITY_BIN_MM_S),
+            damper: DamperAccumulator::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BottomingEvent {
+    pub lap_number: Option<i16>,
+    pub start_lap_time_ms: Option<i32>,
+    pub duration_ms: i32,
+    pub min_ride_height_mm: f32,
+    pub speed_kph: Option<f32>,
+    pub distance_m: Option<f32>,
+    pub pos_x: Option<f32>,
+    pub pos_z: Option<f32>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LapSuspension {
+    pub lap_id: u64,
+    pub lap_number: i16,
+    pub bottoming_events: u32,
+    pub min_ride_height_mm: Option<f32>,
+    // Positive when the nose (or left side) sits higher than the other end.
+    pub max_pitch_mm: Option<f32>,
+    pub min_pitch_mm: Option<f32>,
+    pub max_roll_mm: Option<f32>,
+    pub min_roll_mm: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SuspensionSummary {
+    pub corners: Vec<CornerSuspension>,
+    pub pitch_mm: Histogram,
+    pub roll_mm: Histogram,
+    pub ride_height_mm: Histogram,
+}
+
+struct LastFrame {
+    packet_id: i32,
+    heights_mm: [Option<f32>; 4],
+}
+
+struct ActiveBottoming {
+    event: BottomingEvent,
+    start_packet: i32,
+}
+
+pub struct SuspensionAnalyzer {
+    corners: [CornerAccumulator; 4],
+    pitch: HistogramAccumulator,
+    roll: HistogramAccumulator,
+    ride_height: HistogramAccumulator,
+    last: Option<LastFrame>,
+    active: Option<ActiveBottoming>,
+    bottoming: VecDeque<BottomingEvent>,
+    current: LapSuspension,
+    laps: VecDeque<LapSuspension>,
+}
+
+impl SuspensionAnalyzer {
+    pub fn new() -> Self {
+        Self {
+            corners: [
+                CornerAccumulator::new(),
+                CornerAccumulator::new(),
+                CornerAccumulator::new(),
+                CornerAccumulator::new(),
+            ],
+            pitch: HistogramAccumulator::new(ATTITUDE_BIN_MM),
+            roll: HistogramAccumulator::new(ATTITUDE_BIN_MM),
+            ride_height: HistogramAccumulator::new(TRAVEL_BIN_MM),
+            last: None,
+            active: None,
+            bottoming: VecDeque::with_capacity(BOTTOMING_HISTORY_CAP),
+            current: LapSuspension::default(),
+            laps: VecDeque::with_capacity(LAP_HISTORY_CAP),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn bottoming(&self) -> &VecDeque<BottomingEvent> {
+        &self.bottoming
+    }
+
+    pub fn laps(&self) -> &VecDeque<LapSuspension> {
+        &self.laps
+    }
+
+    pub fn current_lap(&self) -> &LapSuspension {
+        &self.current
+    }
+
+    pub fn update(&mut self, state: &State) {
+        let Some(packet_id) = state.packet_id else {
+            return;
+        };
+        let heights_mm = [
+            state.suspension_fl,
+            state.suspension_fr,
+            state.suspension_rl,
+            state.suspension_rr,
+        ]
+        .map(|height| height.map(|height| height * 1000.0));
+        let packets = match self.last.as_ref() {
+            Some(last) if packet_id == last.packet_id => return,
+            Some(last)
+                if packet_id > last.packet_id && packet_id - last.packet_id <= MAX_PACKET_GAP =>
+            {
+                Some(packet_id - last.packet_id)
+            }
+            _ => None,
+        };
+
+        for (idx, height) in heights_mm.iter().enumerate() {
+            let Some(height) = *height else {
+                continue;
+            };
+            let corner = &mut self.corners[idx];
+            corner.travel.push(height);
+            let previous = self.last.as_ref().and_then(|last| last.heights_mm[idx]);
+            if let (Some(packets), Some(previous)) = (packets, previous) {
+                let velocity = (previous - height) * PACKETS_PER_SECOND / packets as f32;
+                corner.velocity.push(velocity);
+                corner.damper.push(velocity);
+            }
+        }
+
+        if let [Some(fl), Some(fr), Some(rl), Some(rr)] = heights_mm {
+            let pitch = (fl + fr) / 2.0 - (rl + rr) / 2.0;
+            let roll = (fl + rl) / 2.0 - (fr + rr) / 2.0;
+            self.pitch.push(pitch);
+            self.roll.push(roll);
+            let lap = &mut self.current;
+            lap.max_pitch_mm = Some(lap.max_pitch_mm.map_or(pitch, |max| max.max(pitch)));
+            lap.min_pitch_mm = Some(lap.min_pitch_mm.map_or(pitch, |min| min.min(pitch)));
+            lap.max_roll_mm = Some(lap.max_roll_mm.map_or(roll, |max| max.max(roll)));
+            lap.min_roll_mm = Some(lap.min_roll_mm.map_or(roll, |min| min.min(roll)));
+        }
+
+        if let Some(ride_height) = state.ride_height_mm {
+            self.ride_height.push(ride_height);
+            self.current.min_ride_height_mm = Some(
+                self.current
+                    .min_ride_height_mm
+                    .map_or(ride_height, |min| min.min(ride_height)),
+            );
+            self.detect_bottoming(state, packet_id, ride_height, packets.is_some());
+        }
+
+        self.last = Some(LastFrame {
+            packet_id,
+            heights_mm,
+        });
+    }
+
+    fn detect_bottoming(
+        &mut self,
+        state: &State,
+        packet_id: i32,
+        ride_height: f32,
+        continuous: bool,
+    ) {
+        if let Some(active) = self.active.as_mut() {
+            if continuous && ride_height < BOTTOMING_EXIT_MM {
+                active.event.min_ride_height_mm = active.event.min_ride_height_mm.min(ride_height);
+                active.event.duration_ms =
+                    ((packet_id - active.start_packet) as f32 * 1000.0 / PACKETS_PER_SECOND) as i32;
+                return;
+            }
+            self.close_bottoming();
+        }
+        if ride_height <= BOTTOMING_ENTER_MM {
+            self.active = Some(ActiveBottoming {
+                event: BottomingEvent {
+                    lap_number: state.current_lap,
+                    start_lap_time_ms: state.current_lap_time_ms,
+                    duration_ms: 0,
+                    min_ride_height_mm: ride_height,
+                    speed_kph: state.speed_kph,
+                    distance_m: state.lap_distance_m,
+                    pos_x: state.pos_x,
+                    pos_z: state.pos_z,
+                },
+                start_packet: packet_id,
+            });
+        }
+    }
+
+    fn close_bottoming(&mut self) {
+        let Some(active) = self.active.take() else {
+            return;
+        };
+        if self.bottoming.len() >= BOTTOMING_HISTORY_CAP {
+            self.bottoming.pop_front();
+        }
+        self.bottoming.push_back(active.event);
+        self.current.bottoming_events += 1;
+    }
+
+    pub fn finish_lap(&mut self, lap_id: u64, lap_number: i16) {
+        self.close_bottoming();
+        if self.laps.len() >= LAP_HISTORY_CAP {
+            self.laps.pop_front();
+        }
+        let mut lap = std::mem::take(&mut self.current);
+        lap.lap_id = lap_id;
+        lap.lap_number = lap_number;
+        self.laps.push_back(lap);
+    }
+
+    pub fn summary(&self) -> SuspensionSummary {
+        SuspensionSummary {
+            corners: self
+                .corners
+                .iter()
+                .zip(WHEELS)
+                .map(|(corner, wheel)| CornerSuspension {
+                    wheel,
+                    travel_mm: corner.travel.snapshot(),
+                    velocity_mm_s: corner.velocity.snapshot(),
+                    damper: corner.damper.stats(),
+                })
+                .collect(),
+            pitch_mm: self.pitch.snapshot(),
+            roll_mm: self.roll.snapshot(),
+            ride_height_mm: self.ride_height.snapshot(),
+        }
+    }
+}
+
+impl Default for SuspensionAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,184 @@
+// Per-frame lap tracking shared by live ingest and recording replay.
+
+use telemetry_core::session::{LapCompletion, SessionState, SessionTracker};
+
+use super::braking::BrakingAnalyzer;
+use super::distance::{DistanceSource, LapDistanceEstimator};
+use super::dyno::{DynoSample, DynoSampler};
+use super::laps::{LapRecorder, LapTrace};
+use super::performance::{PerformanceResult, PerformanceTester};
+use super::sectors::SectorTimer;
+use super::shifts::ShiftAnalyzer;
+use super::suspension::SuspensionAnalyzer;
+use super::transmission::TransmissionModel;
+use super::tyre_temps::{TyreTemperatureAlert, TyreTemperatureMonitor};
+use super::tyres::{TyreEvent, TyreEventDetector};
+use crate::meta::MetadataStore;
+
+pub struct LapTracker {
+    pub recorder: LapRecorder,
+    pub distance: LapDistanceEstimator,
+    pub distance_source: Option<DistanceSource>,
+    pub sectors: SectorTimer,
+    pub braking: BrakingAnalyzer,
+    pub tyres: TyreEventDetector,
+    pub tyre_temps: TyreTemperatureMonitor,
+    pub shifts: ShiftAnalyzer,
+    pub transmission: TransmissionModel,
+    pub dyno: DynoSampler,
+    pub performance: PerformanceTester,
+    pub suspension: SuspensionAnalyzer,
+}
+
+#[derive(Default)]
+pub struct LapUpdate {
+    pub lap: Option<LapTrace>,
+    pub tyre_events: Vec<TyreEvent>,
+    pub temperature_alerts: Vec<TyreTemperatureAlert>,
+    pub dyno_sample: Option<DynoSample>,
+    pub performance_results: Vec<PerformanceResult>,
+    // Track whose centerline is not cached yet; the caller loads it and hands it to `distance`.
+    pub centerline_needed: Option<i32>,
+}
+
+impl LapTracker {
+    pub fn new() -> Self {
+        Self {
+            recorder: LapRecorder::new(),
+            distance: LapDistanceEstimator::new(),
+            distance_source: None,
+            sectors: SectorTimer::new(),
+            braking: BrakingAnalyzer::new(),
+            tyres: TyreEventDetector::new(),
+            tyre_temps: TyreTemperatureMonitor::new(),
+            shifts: ShiftAnalyzer::new(),
+            transmission: TransmissionModel::new(),
+            dyno: DynoSampler::new(),
+            performance: PerformanceTester::new(),
+            suspension: SuspensionAnalyzer::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.recorder.reset();
+        self.distance.reset();
+        self.distance_source = None;
+        self.sectors.reset();
+        self.braking.reset();
+        self.tyres.reset();
+        self.tyre_temps.reset();
+        self.shifts.reset();
+        self.transmission.reset();
+        self.dyno.reset();
+        self.performance.reset();
+        self.suspension.reset();
+    }
+
+    // Closes a completed lap, then updates lap distance and the per-lap analyzers for the current frame.
+    pub fn update(
+        &mut self,
+        session: &mut SessionTracker,
+        lap_completed: Option<LapCompletion>,
+        meta: &MetadataStore,
+        completed_at_ms: u64,
+    ) -> LapUpdate {
+        let mut tyre_events = Vec::new();
+        let mut temperature_alerts = Vec::new();
+        let mut dyno_sample = None;
+        let mut performance_results = Vec::new();
+        let mut centerline_needed = None;
+        let lap = lap_completed.map(|completion| {
+            self.recorder.finish(
+                completion,
+                session.session_index,
+                session.car_id,
+                session.track_id,
+                completed_at_ms,
+            )
+        });
+        if let Some(lap) = lap.as_ref() {
+            self.sectors
+                .finish_lap(lap.id, lap.lap_number, lap.lap_time_ms);
+            self.braking.finish_lap(lap.id, lap.lap_number);
+            tyre_events.extend(self.tyres.finish_lap(lap.id, lap.lap_number));
+            self.tyre_temps.finish_lap(lap.id, lap.lap_number);
+            self.suspension.finish_lap(lap.id, lap.lap_number);
+        }
+
+        if self.distance.track_id() != session.track_id {
+            let track_id = session.track_id;
+            let centerline = track_id.and_then(|id| meta.cached_track_centerline(id));
+            if centerline.is_none() {
+                centerline_needed = track_id;
+            }
+            self.distance.set_track(
+                track_id,
+                centerline,
+                track_id
+                    .and_then(|id| meta.get_track_info(id))
+                    .and_then(|track| track.length_m),
+            );
+            self.sectors
+                .set_markers(track_id.and_then(|id| meta.get_track_sectors(id)));
+        }
+
+        let in_race = session.session_state == SessionState::InRace;
+        let lap_distance = if in_race {
+            self.distance
+                .update(&session.state, lap_completed.is_some())
+        } else {
+            None
+        };
+        self.distance_source = lap_distance.map(|value| value.source);
+        session.state.lap_distance_m = lap_distance.map(|value| value.distance_m);
+        session.state.lap_progress = lap_distance.and_then(|value| value.progress);
+        if let Some(value) = lap_distance {
+            self.sectors.update(
+                value.distance_m,
+                value.progress,
+                session.state.current_lap_time_ms,
+            );
+        }
+        session.state.sector_index = if in_race {
+            self.sectors.current().map(|sector| sector as u8)
+        } else {
+            None
+        };
+        session.state.sector_delta_ms = self.sectors.last_delta_ms();
+        session.state.theoretical_best_ms = self.sectors.theoretical_best_ms();
+        if in_race {
+            tyre_events.extend(self.tyres.update(&session.state));
+            self.braking.update(&session.state, &self.tyres);
+            temperature_alerts.extend(self.tyre_temps.update(&session.state));
+            self.transmission.update(&session.state, session.car_id);
+            self.shifts.update(&session.state, &self.transmission);
+            dyno_sample = self.dyno.update(&session.state);
+            performance_results = self.performance.update(&session.state);
+            self.suspension.update(&session.state);
+            self.recorder.push(&session.state);
+        } else if session.session_state == SessionState::Paused {
+            self.recorder.mark_paused();
+        }
+        session.state.final_drive_ratio = self.transmission.final_drive();
+        session.state.speed_at_limiter_kph = session
+            .state
+            .gear
+            .and_then(|gear| self.transmission.speed_at_limiter_kph(gear));
+        session.state.theoretical_top_speed_kph = self.transmission.theoretical_top_speed_kph();
+
+        LapUpdate {
+            lap,
+            tyre_events,
+            temperature_alerts,
+            dyno_sample,
+            performance_results,
+            centerline_needed,
+        }
+    }
+}
+
+impl Default for LapTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,227 @@
+// Gearing model from reported gear ratios, tyre size and an inferred final drive.
+
+use std::f32::consts::PI;
+
+use serde::Serialize;
+
+use crate::model::State;
+
+const MAX_GEARS: usize = 8;
+const MIN_SPEED_KPH: f32 = 30.0;
+// Only steady rolling (all four tyres near road speed) is used to infer the final drive.
+const STEADY_SLIP_MIN: f32 = 0.97;
+const STEADY_SLIP_MAX: f32 = 1.03;
+const MIN_CLUTCH_ENGAGED: f32 = 0.99;
+const FINAL_DRIVE_SMOOTHING: f32 = 0.02;
+const MIN_FINAL_DRIVE_SAMPLES: u32 = 30;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct GearSpec {
+    pub gear: i8,
+    pub ratio: f32,
+    pub speed_per_1000rpm_kph: Option<f32>,
+    pub speed_at_limiter_kph: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TransmissionSnapshot {
+    pub final_drive_ratio: Option<f32>,
+    pub final_drive_samples: u32,
+    // Metres travelled per wheel radian, i.e. the rolling radius used by the parser.
+    pub tyre_rolling_radius_m: Option<f32>,
+    pub rev_limiter_rpm: Option<f32>,
+    pub gears: Vec<GearSpec>,
+    pub theoretical_top_speed_kph: Option<f32>,
+    pub observed_top_speed_kph: Option<f32>,
+}
+
+pub struct TransmissionModel {
+    car_id: Option<i32>,
+    ratios: [Option<f32>; MAX_GEARS],
+    rolling_radius_m: Option<f32>,
+    rev_limiter_rpm: Option<f32>,
+    final_drive: Option<f32>,
+    final_drive_samples: u32,
+    observed_top_speed_kph: Option<f32>,
+}
+
+impl TransmissionModel {
+    pub fn new() -> Self {
+        Self {
+            car_id: None,
+            ratios: [None; MAX_GEARS],
+            rolling_radius_m: None,
+            rev_limiter_rpm: None,
+            final_drive: None,
+            final_drive_samples: 0,
+            observed_top_speed_kph: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn update(&mut self, state: &State, car_id: Option<i32>) {
+        if car_id != self.car_id {
+            self.reset();
+            self.car_id = car_id;
+        }
+
+        let ratios = [
+            state.gear_ratio_1,
+            state.gear_ratio_2,
+            state.gear_ratio_3,
+            state.gear_ratio_4,
+            state.gear_ratio_5,
+            state.gear_ratio_6,
+            state.gear_ratio_7,
+            state.gear_ratio_8,
+        ];
+        for (slot, ratio) in self.ratios.iter_mut().zip(ratios) {
+            *slot = ratio.filter(|ratio| ratio.is_finite() && *ratio > 0.0);
+        }
+        if let Some(limiter) = state.rpm_rev_limiter.filter(|value| *value > 0) {
+            self.rev_limiter_rpm = Some(limiter as f32);
+        }
+        let radii: Vec<f32> = [
+            state.tyre_diameter_fl_m,
+            state.tyre_diameter_fr_m,
+            state.tyre_diameter_rl_m,
+            state.tyre_diameter_rr_m,
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|radius| *radius > 0.0)
+        .collect();
+        if radii.len() == 4 {
+            self.rolling_radius_m = Some(radii.iter().sum::<f32>() / 4.0);
+        }
+        if let Some(speed_kph) = state.speed_kph {
+            if self
+                .observed_top_speed_kph
+                .is_none_or(|top| speed_kph > top)
+            {
+                self.observed_top_speed_kph = Some(speed_kph);
+            }
+        }
+
+        self.sample_final_drive(state);
+    }
+
+    fn sample_final_drive(&mut self, state: &State) {
+        let (Some(rpm), Some(speed_kph), Some(gear)) = (state.rpm, state.speed_kph, state.gear)
+        else {
+            return;
+        };
+        if speed_kph < MIN_SPEED_KPH || !(1..=MAX_GEARS as i8).contains(&gear) {
+            return;
+        }
+        if state
+            .clutch_engaged
+            .is_some_and(|engaged| engaged < MIN_CLUTCH_ENGAGED)
+        {
+            return;
+        }
+        let Some(gear_ratio) = self.ratios[gear as usize - 1] else {
+            return;
+        };
+        let slips = [
+            state.tyre_slip_ratio_fl,
+            state.tyre_slip_ratio_fr,
+            state.tyre_slip_ratio_rl,
+            state.tyre_slip_ratio_rr,
+        ];
+        let steady = slips.iter().all(|slip| {
+            slip.is_some_and(|slip| (STEADY_SLIP_MIN..=STEADY_SLIP_MAX).contains(&slip))
+        });
+        let wheel_speeds: Vec<f32> = [
+            state.wheel_speed_fl,
+            state.wheel_speed_fr,
+            state.wheel_speed_rl,
+            state.wheel_speed_rr,
+        ]
+        .into_iter()
+        .flatten()
+        .map(f32::abs)
+        .collect();
+        if !steady || wheel_speeds.len() != 4 {
+            return;
+        }
+        let wheel_rpm = wheel_speeds.iter().sum::<f32>() / 4.0 * 60.0 / (2.0 * PI);
+        if wheel_rpm <= 0.0 {
+            return;
+        }
+        let final_drive = rpm / (wheel_rpm * gear_ratio);
+        if !final_drive.is_finite() {
+            return;
+        }
+        self.final_drive = Some(match self.final_drive {
+            Some(prev) => prev + (final_drive - prev) * FINAL_DRIVE_SMOOTHING,
+            None => final_drive,
+        });
+        self.final_drive_samples = self.final_drive_samples.saturating_add(1);
+    }
+
+    pub fn final_drive(&self) -> Option<f32> {
+        self.final_drive
+            .filter(|_| self.final_drive_samples >= MIN_FINAL_DRIVE_SAMPLES)
+    }
+
+    pub fn speed_per_1000rpm_kph(&self, gear: i8) -> Option<f32> {
+        if !(1..=MAX_GEARS as i8).contains(&gear) {
+            return None;
+        }
+        let ratio = self.ratios[gear as usize - 1]?;
+        let radius = self.rolling_radius_m?;
+        let final_drive = self.final_drive()?;
+        let wheel_rpm = 1000.0 / (ratio * final_drive);
+        Some(wheel_rpm * 2.0 * PI / 60.0 * radius * 3.6)
+    }
+
+    // Engine rpm per km/h of road speed in this gear.
+    pub fn rpm_per_kph(&self, gear: i8) -> Option<f32> {
+        self.speed_per_1000rpm_kph(gear)
+            .filter(|per_1000| *per_1000 > 0.0)
+            .map(|per_1000| 1000.0 / per_1000)
+    }
+
+    pub fn speed_at_limiter_kph(&self, gear: i8) -> Option<f32> {
+        let limiter = self.rev_limiter_rpm?;
+        self.speed_per_1000rpm_kph(gear)
+            .map(|per_1000| per_1000 * limiter / 1000.0)
+    }
+
+    pub fn theoretical_top_speed_kph(&self) -> Option<f32> {
+        (1..=MAX_GEARS as i8)
+            .filter_map(|gear| self.speed_at_limiter_kph(gear))
+            .reduce(f32::max)
+    }
+
+    pub fn snapshot(&self) -> TransmissionSnapshot {
+        TransmissionSnapshot {
+            final_drive_ratio: self.final_drive(),
+            final_drive_samples: self.final_drive_samples,
+            tyre_rolling_radius_m: self.rolling_radius_m,
+            rev_limiter_rpm: self.rev_limiter_rpm,
+            gears: (1..=MAX_GEARS as i8)
+                .filter_map(|gear| {
+                    self.ratios[gear as usize - 1].map(|ratio| GearSpec {
+                        gear,
+                        ratio,
+                        speed_per_1000rpm_kph: self.speed_per_1000rpm_kph(gear),
+                        speed_at_limiter_kph: self.speed_at_limiter_kph(gear),
+                    })
+                })
+                .collect(),
+            theoretical_top_speed_kph: self.theoretical_top_speed_kph(),
+            observed_top_speed_kph: self.observed_top_speed_kph,
+        }
+    }
+}
+
+impl Default for TransmissionModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
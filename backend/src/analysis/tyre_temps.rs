@@ -0,0 +1,329 @@
+// Per-lap tyre temperature statistics, warm-up lap detection and overheat alerts.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::tyres::{Wheel, WHEELS};
+use crate::constants::LAP_HISTORY_CAP;
+use crate::model::State;
+
+const MAX_FRAME_GAP_MS: i32 = 250;
+// An alert re-arms once the tyre cools this far below the threshold.
+const ALERT_REARM_C: f32 = 3.0;
+// Sitting still this long mid-session is treated as a pit stop.
+const PIT_STOP_MS: i32 = 3_000;
+const PIT_STOP_SPEED_KPH: f32 = 5.0;
+const REFUEL_MIN_L: f32 = 1.0;
+// Without lap progress, a stop this early in the lap is taken as the start of an out-lap.
+const OUT_LAP_STOP_MS: i32 = 60_000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PitStop {
+    // Stopped early in the lap: this lap is the out-lap.
+    OutLap,
+    // Stopped late in the lap: the next lap is the out-lap.
+    InLap,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PitLap {
+    // Ended with a pit stop.
+    In,
+    // Started from the pits.
+    Out,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TyreTemperatureConfig {
+    pub optimal_min_c: f32,
+    pub optimal_max_c: f32,
+    pub alert_threshold_c: f32,
+    pub alert_after_ms: i32,
+}
+
+impl Default for TyreTemperatureConfig {
+    fn default() -> Self {
+        Self {
+            optimal_min_c: 70.0,
+            optimal_max_c: 100.0,
+            alert_threshold_c: 105.0,
+            alert_after_ms: 5_000,
+        }
+    }
+}
+
+impl TyreTemperatureConfig {
+    pub fn is_valid(&self) -> bool {
+        self.optimal_min_c < self.optimal_max_c && self.alert_after_ms >= 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct TyreTemperatureStats {
+    pub min_c: Option<f32>,
+    pub avg_c: Option<f32>,
+    pub max_c: Option<f32>,
+    pub below_window_ms: i32,
+    pub above_window_ms: i32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LapTyreTemperatures {
+    pub lap_id: u64,
+    pub lap_number: i16,
+    pub warm_up: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pit_lap: Option<PitLap>,
+    pub fl: TyreTemperatureStats,
+    pub fr: TyreTemperatureStats,
+    pub rl: TyreTemperatureStats,
+    pub rr: TyreTemperatureStats,
+    // Positive when the front (or left) side runs hotter.
+    pub front_rear_balance_c: Option<f32>,
+    pub left_right_balance_c: Option<f32>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TyreTemperatureAlert {
+    pub wheel: Wheel,
+    pub temp_c: f32,
+    pub threshold_c: f32,
+    pub duration_ms: i32,
+    pub lap_number: Option<i16>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Accumulator {
+    min_c: Option<f32>,
+    max_c: Option<f32>,
+    sum_c: f64,
+    count: u32,
+    below_window_ms: i32,
+    above_window_ms: i32,
+}
+
+impl Accumulator {
+    fn push(&mut self, temp_c: f32, dt_ms: i32, config: &TyreTemperatureConfig) {
+        self.min_c = Some(self.min_c.map_or(temp_c, |value| value.min(temp_c)));
+        self.max_c = Some(self.max_c.map_or(temp_c, |value| value.max(temp_c)));
+        self.sum_c += temp_c as f64;
+        self.count += 1;
+        if temp_c < config.optimal_min_c {
+            self.below_window_ms += dt_ms;
+        } else if temp_c > config.optimal_max_c {
+            self.above_window_ms += dt_ms;
+        }
+    }
+
+    fn avg_c(&self) -> Option<f32> {
+        (self.count > 0).then(|| (self.sum_c / self.count as f64) as f32)
+    }
+
+    fn stats(&self) -> TyreTemperatureStats {
+        TyreTemperatureStats {
+            min_c: self.min_c,
+            avg_c: self.avg_c(),
+            max_c: self.max_c,
+            below_window_ms: self.below_window_ms,
+            above_window_ms: self.above_window_ms,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct AlertState {
+    over_ms: i32,
+    fired: bool,
+}
+
+pub struct TyreTemperatureMonitor {
+    config: TyreTemperatureConfig,
+    current: [Accumulator; 4],
+    alerts: [AlertState; 4],
+    last_lap_time_ms: Option<i32>,
+    stationary_ms: i32,
+    last_fuel_l: Option<f32>,
+    pit_stop: Option<PitStop>,
+    // The lap after an in-lap starts from the pits.
+    out_lap_next: bool,
+    // Set at session start and after a pit stop; cleared once a lap runs inside the window.
+    warming_up: bool,
+    laps: VecDeque<LapTyreTemperatures>,
+}
+
+impl TyreTemperatureMonitor {
+    pub fn new() -> Self {
+        Self::with_config(TyreTemperatureConfig::default())
+    }
+
+    fn with_config(config: TyreTemperatureConfig) -> Self {
+        Self {
+            config,
+            current: [Accumulator::default(); 4],
+            alerts: [AlertState::default(); 4],
+            last_lap_time_ms: None,
+            stationary_ms: 0,
+            last_fuel_l: None,
+            pit_stop: None,
+            out_lap_next: false,
+            warming_up: true,
+            laps: VecDeque::with_capacity(LAP_HISTORY_CAP),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::with_config(self.config);
+    }
+
+    pub fn config(&self) -> TyreTemperatureConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: TyreTemperatureConfig) {
+        self.config = config;
+        self.alerts = [AlertState::default(); 4];
+    }
+
+    pub fn laps(&self) -> &VecDeque<LapTyreTemperatures> {
+        &self.laps
+    }
+
+    pub fn warming_up(&self) -> bool {
+        self.warming_up || self.pit_stop == Some(PitStop::OutLap)
+    }
+
+    pub fn current_lap(&self) -> [TyreTemperatureStats; 4] {
+        self.current.map(|acc| acc.stats())
+    }
+
+    // Returns alerts raised on this frame.
+    pub fn update(&mut self, state: &State) -> Vec<TyreTemperatureAlert> {
+        let mut raised = Vec::new();
+        let Some(lap_time_ms) = state.current_lap_time_ms else {
+            return raised;
+        };
+        let dt_ms = match self.last_lap_time_ms {
+            Some(prev) if lap_time_ms > prev && lap_time_ms - prev <= MAX_FRAME_GAP_MS => {
+                lap_time_ms - prev
+            }
+            _ => 0,
+        };
+        self.last_lap_time_ms = Some(lap_time_ms);
+
+        self.detect_pit_stop(state, lap_time_ms, dt_ms);
+
+        let temps = [
+            state.temp_fl_c,
+            state.temp_fr_c,
+            state.temp_rl_c,
+            state.temp_rr_c,
+        ];
+        for (idx, temp_c) in temps.iter().enumerate() {
+            let Some(temp_c) = *temp_c else {
+                continue;
+            };
+            self.current[idx].push(temp_c, dt_ms, &self.config);
+
+            let alert = &mut self.alerts[idx];
+            if temp_c > self.config.alert_threshold_c {
+                alert.over_ms += dt_ms;
+                if !alert.fired && alert.over_ms >= self.config.alert_after_ms {
+                    alert.fired = true;
+                    raised.push(TyreTemperatureAlert {
+                        wheel: WHEELS[idx],
+                        temp_c,
+                        threshold_c: self.config.alert_threshold_c,
+                        duration_ms: alert.over_ms,
+                        lap_number: state.current_lap,
+                    });
+                }
+            } else {
+                alert.over_ms = 0;
+                if temp_c < self.config.alert_threshold_c - ALERT_REARM_C {
+                    alert.fired = false;
+                }
+            }
+        }
+
+        raised
+    }
+
+    pub fn finish_lap(&mut self, lap_id: u64, lap_number: i16) {
+        let averages = self.current.map(|acc| acc.avg_c());
+        let side = |a: usize, b: usize, c: usize, d: usize| match (
+            averages[a],
+            averages[b],
+            averages[c],
+            averages[d],
+        ) {
+            (Some(a), Some(b), Some(c), Some(d)) => Some((a + b) / 2.0 - (c + d) / 2.0),
+            _ => None,
+        };
+        let warm_up = self.warming_up || self.pit_stop == Some(PitStop::OutLap);
+        let pit_lap = match self.pit_stop {
+            Some(PitStop::InLap) => Some(PitLap::In),
+            Some(PitStop::OutLap) => Some(PitLap::Out),
+            None if self.out_lap_next => Some(PitLap::Out),
+            None => None,
+        };
+        self.out_lap_next = self.pit_stop == Some(PitStop::InLap);
+        let in_window = averages
+            .iter()
+            .all(|avg| avg.is_some_and(|avg| avg >= self.config.optimal_min_c));
+        self.warming_up = match self.pit_stop.take() {
+            Some(PitStop::InLap) => true,
+            _ => warm_up && !in_window,
+        };
+
+        if self.laps.len() >= LAP_HISTORY_CAP {
+            self.laps.pop_front();
+        }
+        self.laps.push_back(LapTyreTemperatures {
+            lap_id,
+            lap_number,
+            warm_up,
+            pit_lap,
+            fl: self.current[0].stats(),
+            fr: self.current[1].stats(),
+            rl: self.current[2].stats(),
+            rr: self.current[3].stats(),
+            front_rear_balance_c: side(0, 1, 2, 3),
+            left_right_balance_c: side(0, 2, 1, 3),
+        });
+        self.current = [Accumulator::default(); 4];
+        self.last_lap_time_ms = None;
+    }
+
+    fn detect_pit_stop(&mut self, state: &State, lap_time_ms: i32, dt_ms: i32) {
+        let refuelled = match (self.last_fuel_l, state.fuel_l) {
+            (Some(prev), Some(fuel_l)) => fuel_l - prev >= REFUEL_MIN_L,
+            _ => false,
+        };
+        if state.fuel_l.is_some() {
+            self.last_fuel_l = state.fuel_l;
+        }
+        if state.speed_kph.unwrap_or(0.0) < PIT_STOP_SPEED_KPH {
+            self.stationary_ms += dt_ms;
+        } else {
+            self.stationary_ms = 0;
+        }
+        if self.pit_stop.is_none() && (refuelled || self.stationary_ms >= PIT_STOP_MS) {
+            let early = state
+                .lap_progress
+                .map_or(lap_time_ms < OUT_LAP_STOP_MS, |progress| progress < 0.5);
+            self.pit_stop = Some(if early {
+                PitStop::OutLap
+            } else {
+                PitStop::InLap
+            });
+        }
+    }
+}
+
+impl Default for TyreTemperatureMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,228 @@
+// Lockup and wheelspin detection from per-wheel slip ratios.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::constants::LAP_HISTORY_CAP;
+use crate::model::State;
+
+// Slip ratio is tyre surface speed over car speed: under 1 locks, over 1 spins.
+const LOCKUP_ENTER: f32 = 0.80;
+const LOCKUP_EXIT: f32 = 0.90;
+const WHEELSPIN_ENTER: f32 = 1.20;
+const WHEELSPIN_EXIT: f32 = 1.10;
+const LOCKUP_MIN_BRAKE: f32 = 0.10;
+const WHEELSPIN_MIN_THROTTLE: f32 = 0.20;
+// Slip ratio is meaningless near standstill.
+const MIN_SPEED_KPH: f32 = 20.0;
+// Shorter excursions are treated as noise and not reported.
+const MIN_EVENT_MS: i32 = 100;
+const MAX_FRAME_GAP_MS: i32 = 250;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TyreEventKind {
+    Lockup,
+    Wheelspin,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wheel {
+    Fl,
+    Fr,
+    Rl,
+    Rr,
+}
+
+pub(super) const WHEELS: [Wheel; 4] = [Wheel::Fl, Wheel::Fr, Wheel::Rl, Wheel::Rr];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TyreEvent {
+    pub kind: TyreEventKind,
+    pub wheel: Wheel,
+    pub lap_number: Option<i16>,
+    pub start_lap_time_ms: i32,
+    pub duration_ms: i32,
+    pub distance_m: Option<f32>,
+    pub pos_x: Option<f32>,
+    pub pos_z: Option<f32>,
+    pub speed_kph: f32,
+    pub peak_slip_ratio: f32,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct TyreEventCounts {
+    pub lockups: u32,
+    pub wheelspins: u32,
+    pub lockup_ms: i32,
+    pub wheelspin_ms: i32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LapTyreEvents {
+    pub lap_id: u64,
+    pub lap_number: i16,
+    pub counts: TyreEventCounts,
+}
+
+struct ActiveSlip {
+    event: TyreEvent,
+    last_lap_time_ms: i32,
+}
+
+pub struct TyreEventDetector {
+    active: [Option<ActiveSlip>; 4],
+    current: TyreEventCounts,
+    laps: VecDeque<LapTyreEvents>,
+}
+
+impl TyreEventDetector {
+    pub fn new() -> Self {
+        Self {
+            active: [None, None, None, None],
+            current: TyreEventCounts::default(),
+            laps: VecDeque::with_capacity(LAP_HISTORY_CAP),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn laps(&self) -> &VecDeque<LapTyreEvents> {
+        &self.laps
+    }
+
+    pub fn current_lap(&self) -> &TyreEventCounts {
+        &self.current
+    }
+
+    // Wheels with a lockup in progress as of the last update.
+    pub fn locked_wheels(&self) -> [bool; 4] {
+        self.active.each_ref().map(|active| {
+            active
+                .as_ref()
+                .is_some_and(|active| active.event.kind == TyreEventKind::Lockup)
+        })
+    }
+
+    // Returns events that ended on this frame.
+    pub fn update(&mut self, state: &State) -> Vec<TyreEvent> {
+        let mut finished = Vec::new();
+        let (lap_time_ms, speed_kph) = match (state.current_lap_time_ms, state.speed_kph) {
+            (Some(lap_time_ms), Some(speed_kph)) => (lap_time_ms, speed_kph),
+            _ => return finished,
+        };
+        let brake = state.brake.unwrap_or(0.0);
+        let throttle = state.throttle.unwrap_or(0.0);
+        let slips = [
+            state.tyre_slip_ratio_fl,
+            state.tyre_slip_ratio_fr,
+            state.tyre_slip_ratio_rl,
+            state.tyre_slip_ratio_rr,
+        ];
+
+        for (idx, slip) in slips.iter().enumerate() {
+            let slip = match slip {
+                Some(slip) if speed_kph >= MIN_SPEED_KPH => *slip,
+                _ => {
+                    finished.extend(self.close(idx));
+                    continue;
+                }
+            };
+
+            let still_active = match self.active[idx].as_ref().map(|active| active.event.kind) {
+                Some(TyreEventKind::Lockup) => slip < LOCKUP_EXIT && brake >= LOCKUP_MIN_BRAKE,
+                Some(TyreEventKind::Wheelspin) => {
+                    slip > WHEELSPIN_EXIT && throttle >= WHEELSPIN_MIN_THROTTLE
+                }
+                None => false,
+            };
+            if !still_active {
+                finished.extend(self.close(idx));
+            }
+
+            if let Some(active) = self.active[idx].as_mut() {
+                let dt_ms = lap_time_ms - active.last_lap_time_ms;
+                if dt_ms > 0 && dt_ms <= MAX_FRAME_GAP_MS {
+                    active.event.duration_ms += dt_ms;
+                }
+                if dt_ms >= 0 {
+                    active.last_lap_time_ms = lap_time_ms;
+                }
+                active.event.peak_slip_ratio = match active.event.kind {
+                    TyreEventKind::Lockup => active.event.peak_slip_ratio.min(slip),
+                    TyreEventKind::Wheelspin => active.event.peak_slip_ratio.max(slip),
+                };
+                continue;
+            }
+
+            let kind = if slip < LOCKUP_ENTER && brake >= LOCKUP_MIN_BRAKE {
+                TyreEventKind::Lockup
+            } else if slip > WHEELSPIN_ENTER && throttle >= WHEELSPIN_MIN_THROTTLE {
+                TyreEventKind::Wheelspin
+            } else {
+                continue;
+            };
+            self.active[idx] = Some(ActiveSlip {
+                event: TyreEvent {
+                    kind,
+                    wheel: WHEELS[idx],
+                    lap_number: state.current_lap,
+                    start_lap_time_ms: lap_time_ms,
+                    duration_ms: 0,
+                    distance_m: state.lap_distance_m,
+                    pos_x: state.pos_x,
+                    pos_z: state.pos_z,
+                    speed_kph,
+                    peak_slip_ratio: slip,
+                },
+                last_lap_time_ms: lap_time_ms,
+            });
+        }
+
+        finished
+    }
+
+    // Closes open events at the line so they count towards the lap they started in.
+    pub fn finish_lap(&mut self, lap_id: u64, lap_number: i16) -> Vec<TyreEvent> {
+        let finished: Vec<TyreEvent> = (0..self.active.len())
+            .filter_map(|idx| self.close(idx))
+            .collect();
+        if self.laps.len() >= LAP_HISTORY_CAP {
+            self.laps.pop_front();
+        }
+        self.laps.push_back(LapTyreEvents {
+            lap_id,
+            lap_number,
+            counts: std::mem::take(&mut self.current),
+        });
+        finished
+    }
+
+    fn close(&mut self, idx: usize) -> Option<TyreEvent> {
+        let event = self.active[idx].take()?.event;
+        if event.duration_ms < MIN_EVENT_MS {
+            return None;
+        }
+        match event.kind {
+            TyreEventKind::Lockup => {
+                self.current.lockups += 1;
+                self.current.lockup_ms += event.duration_ms;
+            }
+            TyreEventKind::Wheelspin => {
+                self.current.wheelspins += 1;
+                self.current.wheelspin_ms += event.duration_ms;
+            }
+        }
+        Some(event)
+    }
+}
+
+impl Default for TyreEventDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
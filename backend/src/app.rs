@@ -11,18 +11,33 @@ use tokio::io::BufWriter;
 use tokio::sync::{broadcast, mpsc, oneshot, watch, Mutex, RwLock};
 use tokio::time::Instant;
 
-use crate::constants::{RAW_PACKET_HISTORY, SAMPLE_BUFFER_CAP};
+use crate::analysis::{DeltaTracker, LapTrace, LapTracker, SessionReport};
+use crate::constants::{
+    LAP_HISTORY_CAP, RAW_PACKET_HISTORY, SAMPLE_BUFFER_CAP, SESSION_REPORT_CAP,
+};
 use crate::buffers::RingBuffer;
+use crate::dyno::DynoStore;
+use crate::link::LinkMonitor;
 use crate::meta::MetadataStore;
-use crate::model::Sample;
+use crate::model::{Sample, SampleChannels};
+use crate::performance::PerformanceStore;
+use crate::records::PersonalBestStore;
+use crate::udp::PacketStatsSnapshot;
+use crate::utils::now_epoch_ms;
+use crate::ws::{Broadcaster, EventMessage, FrameBroadcast, Outbound, ReplayBuffer, SharedDelta};
 use telemetry_core::session::SessionTracker;
 pub use telemetry_core::session::SessionState;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub tx: broadcast::Sender<String>,
+    pub tx: broadcast::Sender<Arc<Outbound>>,
     pub sequence: Arc<AtomicU64>,
+    pub frame_sequence: Arc<AtomicU64>,
     pub start_instant: Instant,
+    pub replay: Arc<std::sync::Mutex<ReplayBuffer>>,
+    pub frame_tx: broadcast::Sender<Arc<FrameBroadcast>>,
+    pub events: Arc<std::sync::Mutex<VecDeque<EventMessage>>>,
+    pub state_delta: SharedDelta,
     pub udp_config_tx: watch::Sender<UdpConfig>,
     pub detect_tx: mpsc::Sender<DetectCommand>,
     pub detect_store: Arc<RwLock<DetectStore>>,
@@ -32,12 +47,31 @@ pub struct AppState {
     pub demo_active: Arc<AtomicBool>,
     pub demo_state: Arc<Mutex<DemoState>>,
     pub record_state: Arc<Mutex<RecordState>>,
+    pub personal_bests: Arc<RwLock<PersonalBestStore>>,
+    pub dyno: Arc<RwLock<DynoStore>>,
+    pub performance: Arc<RwLock<PerformanceStore>>,
     pub data_dir: PathBuf,
 }
 
+impl AppState {
+    pub fn broadcaster(&self) -> Broadcaster {
+        Broadcaster {
+            tx: self.tx.clone(),
+            sequence: self.sequence.clone(),
+            frame_sequence: self.frame_sequence.clone(),
+            start_instant: self.start_instant,
+            replay: self.replay.clone(),
+            frame_tx: self.frame_tx.clone(),
+            events: self.events.clone(),
+            state_delta: self.state_delta.clone(),
+        }
+    }
+}
+
 pub struct TelemetryStore {
     pub session: SessionTracker,
     pub samples: RingBuffer<Sample>,
+    pub sample_channels: SampleChannels,
     pub last_packet_id: Option<i32>,
     pub last_source_timestamp_ms: Option<u64>,
     pub last_telemetry_ms: Option<u64>,
@@ -45,6 +79,14 @@ pub struct TelemetryStore {
     pub last_payload_len: Option<usize>,
     pub last_source_ip: Option<IpAddr>,
     pub raw_packets: VecDeque<RawPacketSnapshot>,
+    pub laps: LapTracker,
+    pub lap_history: VecDeque<Arc<LapTrace>>,
+    pub delta: DeltaTracker,
+    pub session_reports: VecDeque<SessionReport>,
+    pub last_car_id: Option<i32>,
+    pub link: LinkMonitor,
+    // Published by the UDP loop once a second.
+    pub packet_stats: PacketStatsSnapshot,
 }
 
 impl TelemetryStore {
@@ -52,6 +94,7 @@ impl TelemetryStore {
         Self {
             session: SessionTracker::new(),
             samples: RingBuffer::new(SAMPLE_BUFFER_CAP),
+            sample_channels: SampleChannels::new(),
             last_packet_id: None,
             last_source_timestamp_ms: None,
             last_telemetry_ms: None,
@@ -59,8 +102,66 @@ impl TelemetryStore {
             last_payload_len: None,
             last_source_ip: None,
             raw_packets: VecDeque::with_capacity(RAW_PACKET_HISTORY),
+            laps: LapTracker::new(),
+            lap_history: VecDeque::with_capacity(LAP_HISTORY_CAP),
+            delta: DeltaTracker::new(),
+            session_reports: VecDeque::with_capacity(SESSION_REPORT_CAP),
+            last_car_id: None,
+            link: LinkMonitor::new(),
+            packet_stats: PacketStatsSnapshot::default(),
         }
     }
+
+    // Builds the report for the session in progress, or the archived one once it has ended.
+    pub fn session_report(
+        &self,
+        session_index: u64,
+        meta: &MetadataStore,
+    ) -> Option<SessionReport> {
+        if session_index == self.session.session_index
+            && self.session.session_state != SessionState::NotInRace
+        {
+            return Some(self.build_session_report(meta, false));
+        }
+        self.session_reports
+            .iter()
+            .find(|report| report.session_index == session_index)
+            .cloned()
+    }
+
+    // Must run before the lap analyzers reset for the next session.
+    pub fn archive_session_report(&mut self, meta: &MetadataStore) {
+        let session_index = self.session.session_index;
+        if !self
+            .lap_history
+            .iter()
+            .any(|lap| lap.session_index == session_index)
+        {
+            return;
+        }
+        let report = self.build_session_report(meta, true);
+        if self.session_reports.len() >= SESSION_REPORT_CAP {
+            self.session_reports.pop_front();
+        }
+        self.session_reports.push_back(report);
+    }
+
+    fn build_session_report(&self, meta: &MetadataStore, complete: bool) -> SessionReport {
+        SessionReport::build(
+            self.session.session_index,
+            self.lap_history.iter().map(|lap| lap.as_ref()),
+            &self.laps,
+            meta,
+            complete,
+            now_epoch_ms(),
+        )
+    }
+}
+
+impl Default for TelemetryStore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub struct RawPacketSnapshot {
@@ -33,6 +33,10 @@ impl<T: Clone> RingBuffer<T> {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn clear(&mut self) {
         self.buf.clear();
         self.head = 0;
@@ -2,13 +2,34 @@
 
 pub const SCHEMA_VERSION: &str = "1.0";
 pub const STATE_INTERVAL_MS: u64 = 50;
+pub const STATE_KEYFRAME_INTERVAL_MS: u64 = 2_000;
+pub const REPLAY_BUFFER_CAP: usize = 2_048;
+pub const REPLAY_WINDOW_MS: u64 = 30_000;
+pub const FRAME_ORDER_WINDOW: usize = 120;
+pub const EVENT_HISTORY_CAP: usize = 100;
 pub const WINDOW_INTERVAL_MS: u64 = 250;
 pub const WINDOW_DURATION_MS: u64 = 5_000;
 pub const WINDOW_STRIDE_MS: u64 = 50;
 pub const SAMPLE_BUFFER_CAP: usize = 600;
+pub const MAX_SAMPLE_CHANNELS: usize = 64;
 pub const RAW_PACKET_HISTORY: usize = 5;
 pub const HEARTBEAT_PORT: u16 = 33739;
 pub const HEARTBEAT_INTERVAL_SECS: u64 = 1;
 pub const HEARTBEAT_BYTE: u8 = 0x41;
+pub const LINK_CHECK_INTERVAL_MS: u64 = 250;
+pub const LINK_STATUS_INTERVAL_MS: u64 = 1_000;
+pub const LINK_STALE_MS: u64 = 2_000;
+pub const LINK_LOST_MS: u64 = 10_000;
+pub const LINK_RATE_WINDOW_MS: u64 = 1_000;
+pub const PACKET_STATS_INTERVAL_MS: u64 = 1_000;
+pub const PACKET_STATS_WINDOW_MS: u64 = 10_000;
 pub const DEMO_DIR: &str = "demo";
 pub const DEMO_FILE: &str = "demo_race.bin";
+pub const LAP_HISTORY_CAP: usize = 40;
+pub const SESSION_REPORT_CAP: usize = 20;
+pub const LAP_TRACE_STRIDE_MS: u64 = 50;
+pub const DEFAULT_SECTOR_COUNT: usize = 3;
+pub const RECORDS_DIR: &str = "records";
+pub const PERSONAL_BEST_FILE: &str = "personal_bests.json";
+pub const DYNO_FILE: &str = "dyno.json";
+pub const PERFORMANCE_FILE: &str = "performance_tests.json";
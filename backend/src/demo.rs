@@ -12,8 +12,10 @@ use crate::app::TelemetryStore;
 use crate::constants::{DEMO_DIR, DEMO_FILE};
 use crate::meta::{self, MetadataStore, PacketMeta, TrackDetector};
 use crate::model::TelemetryFrame;
-use crate::telemetry::apply_frame;
+use crate::telemetry::{apply_frame, FrameContext};
+use crate::udp::FrameOrder;
 use crate::utils::monotonic_ms;
+use crate::ws::Broadcaster;
 use telemetry_core::crypto;
 use telemetry_core::parser;
 
@@ -33,6 +35,31 @@ pub fn resolve_demo_path(data_dir: &Path) -> PathBuf {
     primary
 }
 
+// Each record is the offset from the recording start in ms (u64) and the packet length (u32),
+// little-endian, followed by the encrypted packet.
+pub const RECORD_HEADER_LEN: usize = 12;
+
+pub fn parse_record_header(header: &[u8; RECORD_HEADER_LEN]) -> (u64, usize) {
+    let offset_ms = u64::from_le_bytes(header[0..8].try_into().unwrap());
+    let len = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+    (offset_ms, len)
+}
+
+// Decrypts and parses one recorded packet, with the position taken from the parsed frame.
+pub fn decode_packet(encrypted: &[u8]) -> Option<(TelemetryFrame, PacketMeta)> {
+    let payload = crypto::decrypt_packet(encrypted)?;
+    let frame = parser::parse_telemetry(&payload)?;
+    let position_xz = match (frame.pos_x, frame.pos_z) {
+        (Some(x), Some(z)) => Some((x, z)),
+        _ => None,
+    };
+    let packet_meta = PacketMeta {
+        car_id: meta::parse_packet_meta(&payload).car_id,
+        position_xz,
+    };
+    Some((frame, packet_meta))
+}
+
 pub fn resolve_data_dir() -> PathBuf {
     if let Ok(value) = env::var("APEXTELEMETRY_DATA_DIR") {
         return PathBuf::from(value);
@@ -66,15 +93,27 @@ pub async fn demo_playback_loop(
     store: Arc<RwLock<TelemetryStore>>,
     meta: Arc<MetadataStore>,
     start: Instant,
+    broadcaster: Broadcaster,
     mut cancel: oneshot::Receiver<()>,
 ) -> std::io::Result<()> {
     let mut track_detector = TrackDetector::new();
+    let mut frame_order = FrameOrder::new();
     let mut first_pass = true;
+    let frame_context = FrameContext {
+        store: &store,
+        meta: &meta,
+        broadcaster: &broadcaster,
+        record_state: None,
+        personal_bests: None,
+        dyno: None,
+        performance: None,
+    };
 
     loop {
         if !first_pass {
             reset_store_for_demo(&store).await;
             track_detector.reset();
+            frame_order = FrameOrder::new();
         } else {
             first_pass = false;
         }
@@ -85,7 +124,7 @@ pub async fn demo_playback_loop(
         let mut has_record = false;
 
         loop {
-            let mut header = [0u8; 12];
+            let mut header = [0u8; RECORD_HEADER_LEN];
             let read = tokio::select! {
                 _ = &mut cancel => return Ok(()),
                 read = reader.read_exact(&mut header) => read,
@@ -95,8 +134,7 @@ pub async fn demo_playback_loop(
                 Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
                 Err(err) => return Err(err),
             }
-            let offset_ms = u64::from_le_bytes(header[0..8].try_into().unwrap());
-            let len = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+            let (offset_ms, len) = parse_record_header(&header);
             if len == 0 {
                 continue;
             }
@@ -120,39 +158,18 @@ pub async fn demo_playback_loop(
                 }
             }
 
-            let payload = match crypto::decrypt_packet(&packet) {
-                Some(payload) => payload,
-                None => {
-                    last_offset = offset_ms;
-                    continue;
-                }
-            };
-            let frame: TelemetryFrame = match parser::parse_telemetry(&payload) {
-                Some(frame) => frame,
-                None => {
-                    last_offset = offset_ms;
-                    continue;
-                }
+            let Some((frame, packet_meta)) = decode_packet(&packet) else {
+                last_offset = offset_ms;
+                continue;
             };
-
             let now_ms = monotonic_ms(start);
-            let position_xz = match (frame.pos_x, frame.pos_z) {
-                (Some(x), Some(z)) => Some((x, z)),
-                _ => None,
-            };
-            let packet_meta = meta::parse_packet_meta(&payload);
-            let packet_meta = PacketMeta {
-                car_id: packet_meta.car_id,
-                position_xz,
-            };
             apply_frame(
-                &store,
-                &meta,
+                &frame_context,
                 &mut track_detector,
                 &frame,
                 &packet_meta,
                 now_ms,
-                None,
+                frame_order.check(frame.packet_id),
                 None,
             )
             .await;
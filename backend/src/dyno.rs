@@ -0,0 +1,285 @@
+// Virtual dyno curves accumulated per car across sessions and persisted.
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::analysis::DynoSample;
+use crate::constants::{DYNO_FILE, RECORDS_DIR};
+use crate::utils::{JsonFile, JsonSnapshot};
+
+const RPM_BIN: f32 = 250.0;
+const MAX_RPM_BINS: usize = 80;
+const MIN_BIN_SAMPLES: u32 = 5;
+const MIN_COAST_SAMPLES: u32 = 20;
+// Standard deviation of v² (m²/s²) needed to separate the aero term from rolling resistance.
+const MIN_COAST_SPREAD: f64 = 100.0;
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+struct PowerBin {
+    count: u32,
+    // Per unit mass: inertial power a·v, plus the speed moments needed to add drag later.
+    sum_accel_speed: f64,
+    sum_speed: f64,
+    sum_speed_cubed: f64,
+}
+
+// Least-squares sums for decel = rolling + aero · v².
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+struct CoastFit {
+    count: u32,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_xy: f64,
+}
+
+impl CoastFit {
+    fn push(&mut self, speed_mps: f32, decel_mps2: f32) {
+        let x = (speed_mps as f64).powi(2);
+        let y = decel_mps2 as f64;
+        self.count += 1;
+        self.sum_x += x;
+        self.sum_y += y;
+        self.sum_xx += x * x;
+        self.sum_xy += x * y;
+    }
+
+    fn fit(&self) -> Option<DragModel> {
+        if self.count < MIN_COAST_SAMPLES {
+            return None;
+        }
+        let n = self.count as f64;
+        let mean_x = self.sum_x / n;
+        let mean_y = self.sum_y / n;
+        let var_x = self.sum_xx / n - mean_x * mean_x;
+        if var_x.sqrt() < MIN_COAST_SPREAD {
+            return None;
+        }
+        let aero = (self.sum_xy / n - mean_x * mean_y) / var_x;
+        let (rolling, aero) = if aero > 0.0 {
+            ((mean_y - aero * mean_x).max(0.0), aero)
+        } else {
+            (mean_y.max(0.0), 0.0)
+        };
+        Some(DragModel {
+            rolling_decel_mps2: rolling as f32,
+            aero_decel_per_mps2: aero as f32,
+            samples: self.count,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CarDyno {
+    pub car_id: i32,
+    pub mass_kg: Option<f32>,
+    pub updated_at_ms: u64,
+    coast: CoastFit,
+    bins: Vec<PowerBin>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct DragModel {
+    pub rolling_decel_mps2: f32,
+    // Multiplied by v² (m/s) to give aerodynamic deceleration.
+    pub aero_decel_per_mps2: f32,
+    pub samples: u32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DynoPoint {
+    pub rpm: f32,
+    pub power_w_per_kg: f32,
+    pub torque_nm_per_kg: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_kw: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub torque_nm: Option<f32>,
+    pub samples: u32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DynoCurve {
+    pub car_id: i32,
+    pub mass_kg: Option<f32>,
+    pub updated_at_ms: u64,
+    // Without a coast-down calibration the curve only covers power spent accelerating the car.
+    pub drag: Option<DragModel>,
+    pub coast_samples: u32,
+    pub power_samples: u32,
+    pub peak_power_rpm: Option<f32>,
+    pub peak_torque_rpm: Option<f32>,
+    pub points: Vec<DynoPoint>,
+}
+
+impl CarDyno {
+    fn new(car_id: i32) -> Self {
+        Self {
+            car_id,
+            mass_kg: None,
+            updated_at_ms: 0,
+            coast: CoastFit::default(),
+            bins: vec![PowerBin::default(); MAX_RPM_BINS],
+        }
+    }
+
+    fn record(&mut self, sample: DynoSample) {
+        match sample {
+            DynoSample::Power {
+                rpm,
+                speed_mps,
+                accel_mps2,
+            } => {
+                let Some(bin) = self.bins.get_mut((rpm / RPM_BIN) as usize) else {
+                    return;
+                };
+                let speed = speed_mps as f64;
+                bin.count += 1;
+                bin.sum_accel_speed += accel_mps2 as f64 * speed;
+                bin.sum_speed += speed;
+                bin.sum_speed_cubed += speed.powi(3);
+            }
+            DynoSample::CoastDown {
+                speed_mps,
+                decel_mps2,
+            } => self.coast.push(speed_mps, decel_mps2),
+        }
+    }
+
+    pub fn curve(&self) -> DynoCurve {
+        let drag = self.coast.fit();
+        let (rolling, aero) = drag.map_or((0.0, 0.0), |drag| {
+            (
+                drag.rolling_decel_mps2 as f64,
+                drag.aero_decel_per_mps2 as f64,
+            )
+        });
+        let points: Vec<DynoPoint> = self
+            .bins
+            .iter()
+            .enumerate()
+            .filter(|(_, bin)| bin.count >= MIN_BIN_SAMPLES)
+            .map(|(idx, bin)| {
+                let n = bin.count as f64;
+                let power =
+                    (bin.sum_accel_speed + rolling * bin.sum_speed + aero * bin.sum_speed_cubed)
+                        / n;
+                let rpm = (idx as f32 + 0.5) * RPM_BIN;
+                let torque = power / (rpm as f64 * 2.0 * PI / 60.0);
+                DynoPoint {
+                    rpm,
+                    power_w_per_kg: power as f32,
+                    torque_nm_per_kg: torque as f32,
+                    power_kw: self.mass_kg.map(|mass| power as f32 * mass / 1000.0),
+                    torque_nm: self.mass_kg.map(|mass| torque as f32 * mass),
+                    samples: bin.count,
+                }
+            })
+            .collect();
+        let peak_rpm = |value: fn(&DynoPoint) -> f32| {
+            points
+                .iter()
+                .max_by(|a, b| value(a).total_cmp(&value(b)))
+                .map(|point| point.rpm)
+        };
+        DynoCurve {
+            car_id: self.car_id,
+            mass_kg: self.mass_kg,
+            updated_at_ms: self.updated_at_ms,
+            drag,
+            coast_samples: self.coast.count,
+            power_samples: self.bins.iter().map(|bin| bin.count).sum(),
+            peak_power_rpm: peak_rpm(|point| point.power_w_per_kg),
+            peak_torque_rpm: peak_rpm(|point| point.torque_nm_per_kg),
+            points,
+        }
+    }
+}
+
+pub struct DynoStore {
+    file: JsonFile,
+    cars: HashMap<i32, CarDyno>,
+    dirty: bool,
+}
+
+impl DynoStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(RECORDS_DIR).join(DYNO_FILE);
+        let mut cars = HashMap::new();
+        match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<Vec<CarDyno>>(&bytes) {
+                Ok(list) => {
+                    for mut car in list {
+                        car.bins.resize(MAX_RPM_BINS, PowerBin::default());
+                        cars.insert(car.car_id, car);
+                    }
+                }
+                Err(err) => warn!(?err, path = %path.display(), "dyno file parse failed"),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => warn!(?err, path = %path.display(), "dyno file read failed"),
+        }
+        info!(count = cars.len(), path = %path.display(), "dyno curves loaded");
+        Self {
+            file: JsonFile::new(path),
+            cars,
+            dirty: false,
+        }
+    }
+
+    pub fn get(&self, car_id: i32) -> Option<&CarDyno> {
+        self.cars.get(&car_id)
+    }
+
+    pub fn list(&self) -> Vec<&CarDyno> {
+        let mut list: Vec<&CarDyno> = self.cars.values().collect();
+        list.sort_by_key(|car| car.car_id);
+        list
+    }
+
+    pub fn record(&mut self, car_id: i32, sample: DynoSample, now_ms: u64) {
+        let car = self
+            .cars
+            .entry(car_id)
+            .or_insert_with(|| CarDyno::new(car_id));
+        car.record(sample);
+        car.updated_at_ms = now_ms;
+        self.dirty = true;
+    }
+
+    pub fn set_mass(&mut self, car_id: i32, mass_kg: Option<f32>) -> &CarDyno {
+        let car = self
+            .cars
+            .entry(car_id)
+            .or_insert_with(|| CarDyno::new(car_id));
+        car.mass_kg = mass_kg;
+        self.dirty = true;
+        car
+    }
+
+    pub fn clear(&mut self, car_id: i32) -> bool {
+        let removed = self.cars.remove(&car_id).is_some();
+        self.dirty |= removed;
+        removed
+    }
+
+    // Returns the data to persist if anything changed since the last call.
+    pub fn take_snapshot(&mut self) -> Option<JsonSnapshot<Vec<CarDyno>>> {
+        if !self.dirty {
+            return None;
+        }
+        self.dirty = false;
+        let cars = self.list().into_iter().cloned().collect();
+        Some(self.file.snapshot(cars))
+    }
+}
+
+pub async fn save_dyno(snapshot: JsonSnapshot<Vec<CarDyno>>) {
+    if let Err(err) = snapshot.save().await {
+        warn!(?err, path = %snapshot.path().display(), "failed to save dyno curves");
+    }
+}
@@ -0,0 +1,58 @@
+// Notable server events, pushed to clients as `event` messages and kept in a short history.
+
+use serde::Serialize;
+
+use crate::app::SessionState;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Event {
+    SessionTransition {
+        from: SessionState,
+        to: SessionState,
+        session_index: u64,
+    },
+    LapCompleted {
+        session_index: u64,
+        lap_number: i16,
+        lap_time_ms: i32,
+        partial: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        car_id: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        track_id: Option<i32>,
+    },
+    PersonalBest {
+        car_id: i32,
+        track_id: i32,
+        lap_time_ms: i32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        previous_lap_time_ms: Option<i32>,
+    },
+    TrackDetected {
+        track_id: i32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    // `previous_car_id` is absent for the first car seen since the server started.
+    CarChanged {
+        car_id: i32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        previous_car_id: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    RecordingStarted {
+        path: String,
+    },
+    RecordingStopped {
+        path: String,
+        frames: u64,
+    },
+    DemoStarted {
+        path: String,
+    },
+    DemoStopped {
+        path: String,
+    },
+}
@@ -1,20 +1,28 @@
 // HTTP handlers and routing.
 
+use std::collections::HashMap;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
-use axum::extract::State as AxumState;
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::extract::{Query, State as AxumState};
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
 use axum::routing::get;
 use axum::{Json, Router};
 use serde_json::json;
 use tracing::info;
 
+use crate::analysis::{
+    compare_laps, corner_stats, decode_recording_laps, lap_origin_m, recording_path, segment_track,
+    DeltaTracker, LapRef, LapTrace, PerformanceKind, ReferenceSource, TyreTemperatureConfig,
+};
 use crate::app::{AppState, DetectCommand, DetectEvent, DetectStatus, RecordMode};
 use crate::demo::{demo_default_path, demo_playback_loop, resolve_demo_path, reset_store_for_demo};
+use crate::dyno::{save_dyno, CarDyno};
+use crate::events::Event;
 use crate::recording::{record_status_snapshot, stop_recording_internal, RecordStatusResponse};
-use crate::utils::{hex_encode, now_epoch_ms};
-use crate::ws::ws_handler;
+use crate::utils::{hex_encode, monotonic_ms, now_epoch_ms};
+use crate::ws::{sse_handler, ws_handler};
 
 mod types;
 use types::*;
@@ -40,13 +48,49 @@ pub fn router(app_state: AppState) -> Router {
         .route("/meta/track/:id/geometry", get(get_meta_track_geometry))
         .route("/meta/track/:id/geometry/svg", get(get_meta_track_geometry_svg))
         .route("/meta/current", get(get_meta_current))
+        .route("/records", get(get_records))
+        .route(
+            "/analysis/delta/reference",
+            get(get_delta_reference).post(set_delta_reference),
+        )
+        .route("/sessions/:id/report", get(get_session_report))
+        .route("/analysis/laps", get(get_analysis_laps))
+        .route("/analysis/laps/:id/corners", get(get_analysis_lap_corners))
+        .route("/analysis/sectors", get(get_analysis_sectors))
+        .route("/analysis/braking", get(get_analysis_braking))
+        .route("/analysis/shifts", get(get_analysis_shifts))
+        .route("/analysis/tyres", get(get_analysis_tyres))
+        .route("/analysis/suspension", get(get_analysis_suspension))
+        .route("/analysis/tyres/temperature", get(get_tyre_temperatures))
+        .route(
+            "/analysis/tyres/temperature/config",
+            get(get_tyre_temperature_config).post(set_tyre_temperature_config),
+        )
+        .route("/analysis/compare", get(get_analysis_compare))
+        .route("/car/transmission", get(get_car_transmission))
+        .route("/performance", get(get_performance))
+        .route(
+            "/performance/mode",
+            get(get_performance_mode).post(set_performance_mode),
+        )
+        .route("/dyno", get(get_dyno_list))
+        .route(
+            "/dyno/:car_id",
+            get(get_dyno_car)
+                .post(set_dyno_settings)
+                .delete(clear_dyno_car),
+        )
+        .route("/events", get(get_events))
         .route("/debug/telemetry", get(get_debug_telemetry))
         .route("/ws", get(ws_handler))
+        .route("/stream", get(sse_handler))
         .with_state(app_state)
 }
 
-async fn health() -> impl IntoResponse {
-    Json(HealthResponse { status: "ok" })
+async fn health(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let now_ms = monotonic_ms(app_state.start_instant);
+    let link = app_state.store.read().await.link.status(now_ms);
+    Json(HealthResponse { status: "ok", link })
 }
 
 async fn get_udp_config(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
@@ -216,16 +260,34 @@ async fn start_demo_playback(
     let demo_state = app_state.demo_state.clone();
     let demo_active = app_state.demo_active.clone();
     let start_instant = app_state.start_instant;
+    let broadcaster = app_state.broadcaster();
+    broadcaster.event(Event::DemoStarted {
+        path: path_display.clone(),
+    });
 
     let playback_path = path.clone();
     tokio::spawn(async move {
-        if let Err(err) = demo_playback_loop(playback_path, store, meta, start_instant, cancel_rx).await {
+        if let Err(err) = demo_playback_loop(
+            playback_path,
+            store,
+            meta,
+            start_instant,
+            broadcaster.clone(),
+            cancel_rx,
+        )
+        .await
+        {
             tracing::warn!(?err, "demo playback failed");
         }
         demo_active.store(false, Ordering::Relaxed);
         let mut state = demo_state.lock().await;
         state.active = false;
         state.cancel = None;
+        drop(state);
+        // Covers both a stop request and the recording running out.
+        broadcaster.event(Event::DemoStopped {
+            path: path.to_string_lossy().to_string(),
+        });
     });
 
     Ok(Json(DemoStatusResponse {
@@ -283,7 +345,7 @@ async fn start_recording(
 }
 
 async fn stop_recording(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
-    let response = stop_recording_internal(&app_state.record_state).await;
+    let response = stop_recording_internal(&app_state.record_state, &app_state.broadcaster()).await;
     Json(response)
 }
 
@@ -392,6 +454,530 @@ async fn get_meta_current(AxumState(app_state): AxumState<AppState>) -> impl Int
     })
 }
 
+async fn get_events(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    Json(app_state.broadcaster().event_history())
+}
+
+async fn get_records(
+    AxumState(app_state): AxumState<AppState>,
+    Query(query): Query<RecordsQuery>,
+) -> impl IntoResponse {
+    let personal_bests = app_state.personal_bests.read().await;
+    let records = personal_bests
+        .list()
+        .into_iter()
+        .filter(|record| query.car_id.is_none_or(|id| record.car_id == id))
+        .filter(|record| query.track_id.is_none_or(|id| record.track_id == id))
+        .filter(|record| {
+            query
+                .base_track_id
+                .is_none_or(|id| record.base_track_id == Some(id))
+        })
+        .map(|record| RecordEntry {
+            summary: record.summary(),
+            car_name: app_state
+                .meta
+                .get_car_name(record.car_id)
+                .map(str::to_string),
+            track_name: app_state
+                .meta
+                .get_track_name(record.track_id)
+                .map(str::to_string),
+            samples: query.samples.then(|| record.samples.clone()),
+        })
+        .collect();
+    Json(RecordsResponse { records })
+}
+
+async fn get_delta_reference(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    Json(delta_reference_snapshot(&store.delta))
+}
+
+async fn set_delta_reference(
+    AxumState(app_state): AxumState<AppState>,
+    Json(payload): Json<ReferenceSource>,
+) -> impl IntoResponse {
+    let mut store = app_state.store.write().await;
+    let personal_bests = app_state.personal_bests.read().await;
+    let store = &mut *store;
+    let personal_best = match (store.session.car_id, store.session.track_id) {
+        (Some(car_id), Some(track_id)) => personal_bests.get(car_id, track_id),
+        _ => None,
+    };
+    store.delta.set_source(payload);
+    store.delta.refresh(
+        &store.lap_history,
+        personal_best.map(|record| (record.lap_time_ms, record.samples.as_slice())),
+        store.session.session_index,
+        store.session.car_id,
+        store.session.track_id,
+    );
+    info!(source = ?payload, "delta reference changed");
+    Json(delta_reference_snapshot(&store.delta))
+}
+
+fn delta_reference_snapshot(delta: &DeltaTracker) -> DeltaReferenceResponse {
+    let reference = delta.reference();
+    DeltaReferenceResponse {
+        source: delta.source(),
+        available: reference.is_some(),
+        reference_lap_id: reference.and_then(|reference| reference.lap_id),
+        reference_lap_time_ms: reference.map(|reference| reference.lap_time_ms),
+        reference_length_m: reference.map(|reference| reference.length_m()),
+    }
+}
+
+const DEFAULT_COMPARE_STEP_M: f32 = 5.0;
+const MIN_COMPARE_STEP_M: f32 = 1.0;
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn bad_request(message: String) -> ApiError {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message })))
+}
+
+async fn load_recording_laps(app_state: &AppState, name: &str) -> Result<Vec<LapTrace>, ApiError> {
+    let path = recording_path(&app_state.data_dir, name)
+        .ok_or_else(|| bad_request(format!("invalid recording name '{name}'")))?;
+    if !path.is_file() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": "recording not found",
+                "path": path.to_string_lossy(),
+            })),
+        ));
+    }
+    let meta = app_state.meta.clone();
+    let decoded = tokio::task::spawn_blocking(move || decode_recording_laps(&path, &meta)).await;
+    match decoded {
+        Ok(Ok(laps)) => Ok(laps),
+        Ok(Err(err)) => {
+            tracing::warn!(?err, recording = name, "failed to read recording");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "failed to read recording" })),
+            ))
+        }
+        Err(err) => {
+            tracing::warn!(?err, recording = name, "recording decode task failed");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "error": "failed to decode recording" })),
+            ))
+        }
+    }
+}
+
+async fn get_analysis_laps(
+    AxumState(app_state): AxumState<AppState>,
+    Query(query): Query<LapsQuery>,
+) -> Result<Json<LapsResponse>, ApiError> {
+    let laps = match query.recording.as_deref() {
+        Some(name) => load_recording_laps(&app_state, name)
+            .await?
+            .iter()
+            .map(|lap| {
+                lap.summary(LapRef::recording_id(
+                    name,
+                    lap.session_index,
+                    lap.lap_number,
+                ))
+            })
+            .collect(),
+        None => {
+            let store = app_state.store.read().await;
+            store
+                .lap_history
+                .iter()
+                .map(|lap| lap.summary(lap.id.to_string()))
+                .collect()
+        }
+    };
+    Ok(Json(LapsResponse {
+        recording: query.recording,
+        laps,
+    }))
+}
+
+async fn find_lap(app_state: &AppState, token: &str) -> Result<Arc<LapTrace>, ApiError> {
+    let lap = match token.parse::<LapRef>().map_err(bad_request)? {
+        LapRef::Live(id) => {
+            let store = app_state.store.read().await;
+            store.lap_history.iter().find(|lap| lap.id == id).cloned()
+        }
+        LapRef::Recording {
+            name,
+            session_index,
+            lap_number,
+        } => load_recording_laps(app_state, &name)
+            .await?
+            .into_iter()
+            .find(|lap| lap.session_index == session_index && lap.lap_number == lap_number)
+            .map(Arc::new),
+    };
+    lap.ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "lap not found", "lap": token })),
+        )
+    })
+}
+
+async fn get_analysis_lap_corners(
+    AxumState(app_state): AxumState<AppState>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<LapCornersResponse>, ApiError> {
+    let lap = find_lap(&app_state, &id).await?;
+    let centerline = lap
+        .track_id
+        .and_then(|track_id| app_state.meta.get_track_centerline(track_id));
+    let (centerline, origin_m) = match centerline
+        .and_then(|centerline| lap_origin_m(&centerline, &lap).map(|origin| (centerline, origin)))
+    {
+        Some(value) => value,
+        None => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": "track geometry not available", "track_id": lap.track_id })),
+            ))
+        }
+    };
+    let segments = segment_track(&centerline, origin_m);
+    let corners = corner_stats(&lap, &segments);
+    Ok(Json(LapCornersResponse {
+        lap: lap.summary(id),
+        track_length_m: centerline.length_m(),
+        segments,
+        corners,
+    }))
+}
+
+async fn get_session_report(
+    AxumState(app_state): AxumState<AppState>,
+    axum::extract::Path(id): axum::extract::Path<u64>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Response, ApiError> {
+    let report = app_state
+        .store
+        .read()
+        .await
+        .session_report(id, &app_state.meta)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": "no report for session", "session_index": id })),
+            )
+        })?;
+    let response = match query.format.as_deref().unwrap_or("json") {
+        "json" => Json(report).into_response(),
+        "markdown" | "md" => (
+            [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+            report.to_markdown(),
+        )
+            .into_response(),
+        "html" => Html(report.to_html()).into_response(),
+        other => return Err(bad_request(format!("unsupported report format '{other}'"))),
+    };
+    Ok(response)
+}
+
+async fn get_analysis_sectors(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    let sectors = &store.laps.sectors;
+    Json(SectorsResponse {
+        track_id: store.session.track_id,
+        layout: sectors.layout().clone(),
+        sector_count: sectors.layout().sector_count(),
+        current_sector: sectors.current(),
+        last_sector_delta_ms: sectors.last_delta_ms(),
+        best_sector_ms: sectors.best_ms().to_vec(),
+        theoretical_best_ms: sectors.theoretical_best_ms(),
+        laps: sectors.laps().iter().cloned().collect(),
+    })
+}
+
+async fn get_analysis_braking(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    let braking = &store.laps.braking;
+    Json(BrakingResponse {
+        track_id: store.session.track_id,
+        zones: braking.zones(),
+        laps: braking.laps().iter().cloned().collect(),
+        current_lap: braking.current_lap().to_vec(),
+    })
+}
+
+async fn get_analysis_shifts(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    let shifts = &store.laps.shifts;
+    Json(ShiftsResponse {
+        car_id: store.session.car_id,
+        rev_limiter_rpm: shifts.rev_limiter_rpm(),
+        rev_warning_rpm: shifts.rev_warning_rpm(),
+        gears: shifts.gear_summaries(&store.laps.transmission),
+        torque_curve: shifts.torque_curve(),
+        shifts: shifts.shifts().iter().cloned().collect(),
+    })
+}
+
+async fn get_car_transmission(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    let (car_id, track_id) = (store.session.car_id, store.session.track_id);
+    Json(TransmissionResponse {
+        car_id,
+        car_name: car_id
+            .and_then(|id| app_state.meta.get_car_info(id))
+            .map(|info| info.name.clone()),
+        track_id,
+        longest_straight_m: track_id
+            .and_then(|id| app_state.meta.get_track_info(id))
+            .and_then(|track| track.longest_straight_m),
+        transmission: store.laps.transmission.snapshot(),
+    })
+}
+
+async fn get_performance(
+    AxumState(app_state): AxumState<AppState>,
+    Query(query): Query<PerformanceQuery>,
+) -> impl IntoResponse {
+    let enabled = app_state.store.read().await.laps.performance.enabled();
+    let performance = app_state.performance.read().await;
+    let cars = performance
+        .car_ids()
+        .into_iter()
+        .filter(|car_id| query.car_id.is_none_or(|id| *car_id == id))
+        .map(|car_id| CarPerformance {
+            car_id,
+            car_name: app_state.meta.get_car_name(car_id).map(str::to_string),
+            best: PerformanceKind::ALL
+                .into_iter()
+                .filter_map(|kind| performance.best(car_id, kind).cloned())
+                .collect(),
+            results: performance.results(car_id).to_vec(),
+        })
+        .collect();
+    Json(PerformanceResponse { enabled, cars })
+}
+
+async fn get_performance_mode(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    Json(PerformanceMode {
+        enabled: store.laps.performance.enabled(),
+    })
+}
+
+async fn set_performance_mode(
+    AxumState(app_state): AxumState<AppState>,
+    Json(payload): Json<PerformanceMode>,
+) -> impl IntoResponse {
+    let mut store = app_state.store.write().await;
+    store.laps.performance.set_enabled(payload.enabled);
+    info!(enabled = payload.enabled, "performance test mode changed");
+    Json(payload)
+}
+
+fn dyno_entry(app_state: &AppState, car: &CarDyno) -> DynoEntry {
+    DynoEntry {
+        car_name: app_state.meta.get_car_name(car.car_id).map(str::to_string),
+        curve: car.curve(),
+    }
+}
+
+async fn get_dyno_list(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let dyno = app_state.dyno.read().await;
+    let cars = dyno
+        .list()
+        .into_iter()
+        .map(|car| dyno_entry(&app_state, car))
+        .collect();
+    Json(DynoListResponse { cars })
+}
+
+async fn get_dyno_car(
+    AxumState(app_state): AxumState<AppState>,
+    axum::extract::Path(car_id): axum::extract::Path<i32>,
+) -> Result<Json<DynoEntry>, ApiError> {
+    let dyno = app_state.dyno.read().await;
+    match dyno.get(car_id) {
+        Some(car) => Ok(Json(dyno_entry(&app_state, car))),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": "no dyno data for car", "car_id": car_id })),
+        )),
+    }
+}
+
+async fn set_dyno_settings(
+    AxumState(app_state): AxumState<AppState>,
+    axum::extract::Path(car_id): axum::extract::Path<i32>,
+    Json(payload): Json<DynoSettingsRequest>,
+) -> Result<Json<DynoEntry>, ApiError> {
+    if payload
+        .mass_kg
+        .is_some_and(|mass| !mass.is_finite() || mass <= 0.0)
+    {
+        return Err(bad_request("mass_kg must be positive".to_string()));
+    }
+    let (entry, snapshot) = {
+        let mut dyno = app_state.dyno.write().await;
+        let entry = dyno_entry(&app_state, dyno.set_mass(car_id, payload.mass_kg));
+        (entry, dyno.take_snapshot())
+    };
+    info!(car_id, mass_kg = ?payload.mass_kg, "dyno settings changed");
+    if let Some(snapshot) = snapshot {
+        save_dyno(snapshot).await;
+    }
+    Ok(Json(entry))
+}
+
+async fn clear_dyno_car(
+    AxumState(app_state): AxumState<AppState>,
+    axum::extract::Path(car_id): axum::extract::Path<i32>,
+) -> Result<StatusCode, ApiError> {
+    let snapshot = {
+        let mut dyno = app_state.dyno.write().await;
+        if !dyno.clear(car_id) {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": "no dyno data for car", "car_id": car_id })),
+            ));
+        }
+        dyno.take_snapshot()
+    };
+    info!(car_id, "dyno data cleared");
+    if let Some(snapshot) = snapshot {
+        save_dyno(snapshot).await;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_analysis_tyres(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    let tyres = &store.laps.tyres;
+    Json(TyreEventsResponse {
+        laps: tyres.laps().iter().cloned().collect(),
+        current_lap: tyres.current_lap().clone(),
+    })
+}
+
+async fn get_analysis_suspension(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    let suspension = &store.laps.suspension;
+    Json(SuspensionResponse {
+        track_id: store.session.track_id,
+        summary: suspension.summary(),
+        bottoming: suspension.bottoming().iter().cloned().collect(),
+        laps: suspension.laps().iter().cloned().collect(),
+        current_lap: suspension.current_lap().clone(),
+    })
+}
+
+async fn get_tyre_temperatures(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    let monitor = &store.laps.tyre_temps;
+    let [fl, fr, rl, rr] = monitor.current_lap();
+    Json(TyreTemperaturesResponse {
+        config: monitor.config(),
+        warming_up: monitor.warming_up(),
+        current_lap: CurrentTyreTemperatures { fl, fr, rl, rr },
+        laps: monitor.laps().iter().cloned().collect(),
+    })
+}
+
+async fn get_tyre_temperature_config(
+    AxumState(app_state): AxumState<AppState>,
+) -> impl IntoResponse {
+    let store = app_state.store.read().await;
+    Json(store.laps.tyre_temps.config())
+}
+
+async fn set_tyre_temperature_config(
+    AxumState(app_state): AxumState<AppState>,
+    Json(payload): Json<TyreTemperatureConfig>,
+) -> Result<Json<TyreTemperatureConfig>, ApiError> {
+    if !payload.is_valid() {
+        return Err(bad_request(
+            "optimal_min_c must be below optimal_max_c and alert_after_ms non-negative".to_string(),
+        ));
+    }
+    let mut store = app_state.store.write().await;
+    store.laps.tyre_temps.set_config(payload);
+    info!(config = ?payload, "tyre temperature config changed");
+    Ok(Json(payload))
+}
+
+async fn get_analysis_compare(
+    AxumState(app_state): AxumState<AppState>,
+    Query(query): Query<CompareQuery>,
+) -> impl IntoResponse {
+    let tokens: Vec<&str> = query
+        .laps
+        .split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .collect();
+    if tokens.len() < 2 {
+        return Err(bad_request("at least two laps are required".to_string()));
+    }
+    let refs = tokens
+        .iter()
+        .map(|token| token.parse::<LapRef>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(bad_request)?;
+
+    let live: Vec<Arc<LapTrace>> = {
+        let store = app_state.store.read().await;
+        store.lap_history.iter().cloned().collect()
+    };
+    let mut recordings: HashMap<String, Vec<LapTrace>> = HashMap::new();
+    for lap_ref in &refs {
+        if let LapRef::Recording { name, .. } = lap_ref {
+            if !recordings.contains_key(name) {
+                let laps = load_recording_laps(&app_state, name).await?;
+                recordings.insert(name.clone(), laps);
+            }
+        }
+    }
+
+    let mut selected: Vec<(String, &LapTrace)> = Vec::with_capacity(refs.len());
+    for (token, lap_ref) in tokens.iter().zip(refs.iter()) {
+        let lap = match lap_ref {
+            LapRef::Live(id) => live
+                .iter()
+                .find(|lap| lap.id == *id)
+                .map(|lap| lap.as_ref()),
+            LapRef::Recording {
+                name,
+                session_index,
+                lap_number,
+            } => recordings.get(name).and_then(|laps| {
+                laps.iter().find(|lap| {
+                    lap.session_index == *session_index && lap.lap_number == *lap_number
+                })
+            }),
+        };
+        match lap {
+            Some(lap) => selected.push((token.to_string(), lap)),
+            None => {
+                return Err((
+                    StatusCode::NOT_FOUND,
+                    Json(json!({ "error": "lap not found", "lap": token })),
+                ))
+            }
+        }
+    }
+
+    let step_m = query
+        .step_m
+        .unwrap_or(DEFAULT_COMPARE_STEP_M)
+        .max(MIN_COMPARE_STEP_M);
+    compare_laps(&selected, step_m)
+        .map(Json)
+        .ok_or_else(|| bad_request("laps have no distance data to compare".to_string()))
+}
+
 async fn get_debug_telemetry(AxumState(app_state): AxumState<AppState>) -> impl IntoResponse {
     let store = app_state.store.read().await;
     let state = store.session.state.clone();
@@ -423,6 +1009,9 @@ async fn get_debug_telemetry(AxumState(app_state): AxumState<AppState>) -> impl
             total_positions: state.total_positions,
             car_id: store.session.car_id,
             track_id: store.session.track_id,
+            lap_distance_m: state.lap_distance_m,
+            lap_progress: state.lap_progress,
+            lap_distance_source: store.laps.distance_source,
         },
         powertrain: DebugPowertrain {
             speed_kph: state.speed_kph,
@@ -534,5 +1123,6 @@ async fn get_debug_telemetry(AxumState(app_state): AxumState<AppState>) -> impl
             last_telemetry_ms: store.last_telemetry_ms,
             last_source_timestamp_ms: store.last_source_timestamp_ms,
         },
+        packets: store.packet_stats.clone(),
     })
 }
@@ -2,13 +2,26 @@
 
 use std::net::IpAddr;
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
+use crate::analysis::{
+    BottomingEvent, BrakingEvent, BrakingZone, CornerStats, DistanceSource, GearShiftSummary,
+    LapBraking, LapPoint, LapSectors, LapSummary, LapSuspension, LapTyreEvents,
+    LapTyreTemperatures, ReferenceSource, SectorLayout, ShiftEvent, SuspensionSummary, TorquePoint,
+    TrackSegment, TransmissionSnapshot, TyreEventCounts, TyreTemperatureConfig,
+    TyreTemperatureStats,
+};
 use crate::app::DetectStatus;
+use crate::dyno::DynoCurve;
+use crate::link::LinkStatus;
+use crate::performance::PerformanceRecord;
+use crate::records::PersonalBestSummary;
+use crate::udp::PacketStatsSnapshot;
 
 #[derive(Serialize)]
 pub struct HealthResponse {
     pub status: &'static str,
+    pub link: LinkStatus,
 }
 
 #[derive(Serialize)]
@@ -87,6 +100,7 @@ pub struct DebugTelemetryResponse {
     pub dynamics: DebugDynamics,
     pub flags: DebugFlags,
     pub raw: DebugRaw,
+    pub packets: PacketStatsSnapshot,
 }
 
 #[derive(Serialize)]
@@ -103,6 +117,9 @@ pub struct DebugSession {
     pub total_positions: Option<i16>,
     pub car_id: Option<i32>,
     pub track_id: Option<i32>,
+    pub lap_distance_m: Option<f32>,
+    pub lap_progress: Option<f32>,
+    pub lap_distance_source: Option<DistanceSource>,
 }
 
 #[derive(Serialize)]
@@ -232,3 +249,180 @@ pub struct DebugRaw {
     pub last_telemetry_ms: Option<u64>,
     pub last_source_timestamp_ms: Option<u64>,
 }
+
+#[derive(Deserialize)]
+pub struct RecordsQuery {
+    pub car_id: Option<i32>,
+    pub track_id: Option<i32>,
+    pub base_track_id: Option<i32>,
+    #[serde(default)]
+    pub samples: bool,
+}
+
+#[derive(Serialize)]
+pub struct RecordsResponse {
+    pub records: Vec<RecordEntry>,
+}
+
+#[derive(Serialize)]
+pub struct RecordEntry {
+    #[serde(flatten)]
+    pub summary: PersonalBestSummary,
+    pub car_name: Option<String>,
+    pub track_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub samples: Option<Vec<LapPoint>>,
+}
+
+#[derive(Serialize)]
+pub struct DeltaReferenceResponse {
+    #[serde(flatten)]
+    pub source: ReferenceSource,
+    pub available: bool,
+    pub reference_lap_id: Option<u64>,
+    pub reference_lap_time_ms: Option<i32>,
+    pub reference_length_m: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct CompareQuery {
+    pub laps: String,
+    pub step_m: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    pub format: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LapsQuery {
+    pub recording: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct LapsResponse {
+    pub recording: Option<String>,
+    pub laps: Vec<LapSummary>,
+}
+
+#[derive(Serialize)]
+pub struct SectorsResponse {
+    pub track_id: Option<i32>,
+    pub layout: SectorLayout,
+    pub sector_count: usize,
+    pub current_sector: Option<usize>,
+    pub last_sector_delta_ms: Option<i32>,
+    pub best_sector_ms: Vec<Option<i32>>,
+    pub theoretical_best_ms: Option<i32>,
+    pub laps: Vec<LapSectors>,
+}
+
+#[derive(Serialize)]
+pub struct LapCornersResponse {
+    pub lap: LapSummary,
+    pub track_length_m: f32,
+    pub segments: Vec<TrackSegment>,
+    pub corners: Vec<CornerStats>,
+}
+
+#[derive(Serialize)]
+pub struct BrakingResponse {
+    pub track_id: Option<i32>,
+    pub zones: Vec<BrakingZone>,
+    pub laps: Vec<LapBraking>,
+    pub current_lap: Vec<BrakingEvent>,
+}
+
+#[derive(Serialize)]
+pub struct TyreEventsResponse {
+    pub laps: Vec<LapTyreEvents>,
+    pub current_lap: TyreEventCounts,
+}
+
+#[derive(Serialize)]
+pub struct TyreTemperaturesResponse {
+    pub config: TyreTemperatureConfig,
+    pub warming_up: bool,
+    pub current_lap: CurrentTyreTemperatures,
+    pub laps: Vec<LapTyreTemperatures>,
+}
+
+#[derive(Serialize)]
+pub struct CurrentTyreTemperatures {
+    pub fl: TyreTemperatureStats,
+    pub fr: TyreTemperatureStats,
+    pub rl: TyreTemperatureStats,
+    pub rr: TyreTemperatureStats,
+}
+
+#[derive(Serialize)]
+pub struct ShiftsResponse {
+    pub car_id: Option<i32>,
+    pub rev_limiter_rpm: Option<f32>,
+    pub rev_warning_rpm: Option<f32>,
+    pub gears: Vec<GearShiftSummary>,
+    pub torque_curve: Vec<TorquePoint>,
+    pub shifts: Vec<ShiftEvent>,
+}
+
+#[derive(Serialize)]
+pub struct DynoListResponse {
+    pub cars: Vec<DynoEntry>,
+}
+
+#[derive(Serialize)]
+pub struct DynoEntry {
+    pub car_name: Option<String>,
+    #[serde(flatten)]
+    pub curve: DynoCurve,
+}
+
+#[derive(Deserialize)]
+pub struct DynoSettingsRequest {
+    pub mass_kg: Option<f32>,
+}
+
+#[derive(Deserialize)]
+pub struct PerformanceQuery {
+    pub car_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PerformanceMode {
+    pub enabled: bool,
+}
+
+#[derive(Serialize)]
+pub struct PerformanceResponse {
+    pub enabled: bool,
+    pub cars: Vec<CarPerformance>,
+}
+
+#[derive(Serialize)]
+pub struct CarPerformance {
+    pub car_id: i32,
+    pub car_name: Option<String>,
+    pub best: Vec<PerformanceRecord>,
+    pub results: Vec<PerformanceRecord>,
+}
+
+#[derive(Serialize)]
+pub struct SuspensionResponse {
+    pub track_id: Option<i32>,
+    #[serde(flatten)]
+    pub summary: SuspensionSummary,
+    pub bottoming: Vec<BottomingEvent>,
+    pub laps: Vec<LapSuspension>,
+    pub current_lap: LapSuspension,
+}
+
+#[derive(Serialize)]
+pub struct TransmissionResponse {
+    pub car_id: Option<i32>,
+    pub car_name: Option<String>,
+    pub track_id: Option<i32>,
+    pub longest_straight_m: Option<f32>,
+    #[serde(flatten)]
+    pub transmission: TransmissionSnapshot,
+}
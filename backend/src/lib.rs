@@ -1,14 +1,20 @@
 // Crate root for ApexTelemetry for GT server modules.
 
+pub mod analysis;
 pub mod app;
 pub mod buffers;
 pub mod constants;
 pub mod demo;
+pub mod dyno;
+pub mod events;
 pub mod http;
+pub mod link;
 pub mod meta;
 pub mod model;
 pub mod net;
+pub mod performance;
 pub mod recording;
+pub mod records;
 pub mod tasks;
 pub mod telemetry;
 pub mod udp;
@@ -0,0 +1,135 @@
+// Telemetry link health: whether the PS5 is being asked for telemetry and whether it arrives.
+
+use std::collections::VecDeque;
+use std::net::IpAddr;
+
+use serde::Serialize;
+
+use crate::app::HeartbeatMode;
+use crate::constants::{LINK_LOST_MS, LINK_RATE_WINDOW_MS, LINK_STALE_MS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkState {
+    // No heartbeat target and no packets.
+    Idle,
+    // Auto-detect is broadcasting heartbeats.
+    Searching,
+    // Heartbeats go to the PS5 but nothing has arrived since they started.
+    Waiting,
+    Streaming,
+    // Packets stopped recently, e.g. the game is loading or the PS5 went to the menu.
+    Stale,
+    Lost,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LinkStatus {
+    pub state: LinkState,
+    // Monotonic time the link entered `state`.
+    pub since_ms: u64,
+    pub heartbeat_mode: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ps5_ip: Option<IpAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_packet_age_ms: Option<u64>,
+    pub packets_per_sec: f32,
+}
+
+pub struct LinkMonitor {
+    mode: HeartbeatMode,
+    mode_since_ms: u64,
+    last_packet_ms: Option<u64>,
+    // Arrival times within the rate window.
+    packets: VecDeque<u64>,
+    state: LinkState,
+    since_ms: u64,
+}
+
+impl LinkMonitor {
+    pub fn new() -> Self {
+        Self {
+            mode: HeartbeatMode::Stop,
+            mode_since_ms: 0,
+            last_packet_ms: None,
+            packets: VecDeque::new(),
+            state: LinkState::Idle,
+            since_ms: 0,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: HeartbeatMode, now_ms: u64) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.mode_since_ms = now_ms;
+        }
+    }
+
+    pub fn record_packet(&mut self, now_ms: u64) {
+        self.last_packet_ms = Some(now_ms);
+        self.packets.push_back(now_ms);
+        while self
+            .packets
+            .front()
+            .is_some_and(|t_ms| now_ms.saturating_sub(*t_ms) >= LINK_RATE_WINDOW_MS)
+        {
+            self.packets.pop_front();
+        }
+    }
+
+    // Re-evaluates the state from the heartbeat mode and packet age; true when it changed.
+    pub fn update(&mut self, now_ms: u64) -> bool {
+        let age_ms = self
+            .last_packet_ms
+            .map(|last_ms| now_ms.saturating_sub(last_ms));
+        let state = match (&self.mode, age_ms) {
+            (_, Some(age_ms)) if age_ms < LINK_STALE_MS => LinkState::Streaming,
+            (HeartbeatMode::Broadcast, _) => LinkState::Searching,
+            (HeartbeatMode::Unicast(_), _)
+                if self
+                    .last_packet_ms
+                    .is_none_or(|last_ms| last_ms < self.mode_since_ms) =>
+            {
+                LinkState::Waiting
+            }
+            (_, Some(age_ms)) if age_ms < LINK_LOST_MS => LinkState::Stale,
+            (HeartbeatMode::Unicast(_), _) => LinkState::Lost,
+            (HeartbeatMode::Stop, _) => LinkState::Idle,
+        };
+        if state == self.state {
+            return false;
+        }
+        self.state = state;
+        self.since_ms = now_ms;
+        true
+    }
+
+    pub fn status(&self, now_ms: u64) -> LinkStatus {
+        let (heartbeat_mode, ps5_ip) = match self.mode {
+            HeartbeatMode::Stop => ("stop", None),
+            HeartbeatMode::Broadcast => ("broadcast", None),
+            HeartbeatMode::Unicast(ip) => ("unicast", Some(ip)),
+        };
+        let recent = self
+            .packets
+            .iter()
+            .filter(|t_ms| now_ms.saturating_sub(**t_ms) < LINK_RATE_WINDOW_MS)
+            .count();
+        LinkStatus {
+            state: self.state,
+            since_ms: self.since_ms,
+            heartbeat_mode,
+            ps5_ip,
+            last_packet_age_ms: self
+                .last_packet_ms
+                .map(|last_ms| now_ms.saturating_sub(last_ms)),
+            packets_per_sec: recent as f32 * 1_000.0 / LINK_RATE_WINDOW_MS as f32,
+        }
+    }
+}
+
+impl Default for LinkMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,5 +1,6 @@
 // Minimal telemetry pipeline server for ApexTelemetry for GT.
 
+use std::collections::VecDeque;
 use std::env;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::atomic::{AtomicBool, AtomicU64};
@@ -10,11 +11,18 @@ use tokio::time::Instant;
 use tracing::{info, warn};
 
 use apextelemetry_for_gt_server::app::{AppState, DetectStore, RecordState, TelemetryStore, UdpConfig};
+use apextelemetry_for_gt_server::constants::EVENT_HISTORY_CAP;
 use apextelemetry_for_gt_server::demo::resolve_data_dir;
+use apextelemetry_for_gt_server::dyno::DynoStore;
 use apextelemetry_for_gt_server::http;
 use apextelemetry_for_gt_server::meta::MetadataStore;
+use apextelemetry_for_gt_server::performance::PerformanceStore;
+use apextelemetry_for_gt_server::records::PersonalBestStore;
 use apextelemetry_for_gt_server::tasks;
 use apextelemetry_for_gt_server::udp;
+use apextelemetry_for_gt_server::ws::{
+    Broadcaster, FrameBroadcast, Outbound, ReplayBuffer, SharedDelta,
+};
 
 #[tokio::main]
 async fn main() {
@@ -46,9 +54,22 @@ async fn main() {
     let data_dir_path = resolve_data_dir();
     let meta = Arc::new(MetadataStore::load(&data_dir_path));
 
-    let store = Arc::new(RwLock::new(TelemetryStore::new()));
+    let mut telemetry_store = TelemetryStore::new();
+    if let Ok(value) = env::var("SAMPLE_CHANNELS") {
+        let channels: Vec<String> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        if let Err(err) = telemetry_store.sample_channels.set_defaults(&channels) {
+            warn!(%err, "ignoring SAMPLE_CHANNELS");
+        }
+    }
+    let store = Arc::new(RwLock::new(telemetry_store));
 
-    let (tx, _) = broadcast::channel::<String>(256);
+    let (tx, _) = broadcast::channel::<Arc<Outbound>>(256);
+    let (frame_tx, _) = broadcast::channel::<Arc<FrameBroadcast>>(256);
     let (udp_config_tx, udp_config_rx) = watch::channel(UdpConfig {
         bind_addr: udp_bind_addr,
         ps5_ip: None,
@@ -57,10 +78,29 @@ async fn main() {
     let detect_store = Arc::new(RwLock::new(DetectStore::default()));
     let detect_sequence = Arc::new(AtomicU64::new(0));
     let sequence = Arc::new(AtomicU64::new(0));
+    let frame_sequence = Arc::new(AtomicU64::new(0));
+    let replay = Arc::new(std::sync::Mutex::new(ReplayBuffer::new()));
+    let events = Arc::new(std::sync::Mutex::new(VecDeque::with_capacity(
+        EVENT_HISTORY_CAP,
+    )));
+    let state_delta = SharedDelta::new();
     let demo_active = Arc::new(AtomicBool::new(false));
     let demo_state = Arc::new(Mutex::new(Default::default()));
     let record_state = Arc::new(Mutex::new(RecordState::default()));
     let start_instant = Instant::now();
+    let personal_bests = Arc::new(RwLock::new(PersonalBestStore::load(&data_dir_path)));
+    let dyno = Arc::new(RwLock::new(DynoStore::load(&data_dir_path)));
+    let performance = Arc::new(RwLock::new(PerformanceStore::load(&data_dir_path)));
+    let broadcaster = Broadcaster {
+        tx: tx.clone(),
+        sequence: sequence.clone(),
+        frame_sequence: frame_sequence.clone(),
+        start_instant,
+        replay: replay.clone(),
+        frame_tx: frame_tx.clone(),
+        events: events.clone(),
+        state_delta: state_delta.clone(),
+    };
 
     let udp_store = store.clone();
     let udp_meta = meta.clone();
@@ -69,6 +109,10 @@ async fn main() {
     let udp_config_tx_udp = udp_config_tx.clone();
     let udp_demo_active = demo_active.clone();
     let udp_record_state = record_state.clone();
+    let udp_personal_bests = personal_bests.clone();
+    let udp_dyno = dyno.clone();
+    let udp_performance = performance.clone();
+    let udp_broadcaster = broadcaster.clone();
     tokio::spawn(async move {
         if let Err(err) = udp::udp_loop(
             udp_port,
@@ -81,6 +125,10 @@ async fn main() {
             udp_start,
             udp_demo_active,
             udp_record_state,
+            udp_personal_bests,
+            udp_dyno,
+            udp_performance,
+            udp_broadcaster,
         )
         .await
         {
@@ -89,19 +137,21 @@ async fn main() {
     });
 
     let state_store = store.clone();
-    let state_tx = tx.clone();
-    let state_seq = sequence.clone();
-    let state_start = start_instant;
+    let state_broadcaster = broadcaster.clone();
     tokio::spawn(async move {
-        tasks::state_update_task(state_store, state_tx, state_seq, state_start).await;
+        tasks::state_update_task(state_store, state_broadcaster).await;
     });
 
     let samples_store = store.clone();
-    let samples_tx = tx.clone();
-    let samples_seq = sequence.clone();
-    let samples_start = start_instant;
+    let samples_broadcaster = broadcaster.clone();
+    tokio::spawn(async move {
+        tasks::samples_window_task(samples_store, samples_broadcaster).await;
+    });
+
+    let link_store = store.clone();
+    let link_broadcaster = broadcaster.clone();
     tokio::spawn(async move {
-        tasks::samples_window_task(samples_store, samples_tx, samples_seq, samples_start).await;
+        tasks::link_status_task(link_store, link_broadcaster).await;
     });
 
     let heartbeat_config_rx = udp_config_tx.subscribe();
@@ -124,7 +174,12 @@ async fn main() {
     let app_state = AppState {
         tx,
         sequence,
+        frame_sequence,
         start_instant,
+        replay,
+        frame_tx,
+        events,
+        state_delta,
         udp_config_tx,
         detect_tx,
         detect_store,
@@ -134,6 +189,9 @@ async fn main() {
         demo_active,
         demo_state,
         record_state,
+        personal_bests,
+        dyno,
+        performance,
         data_dir: data_dir_path,
     };
 
@@ -0,0 +1,134 @@
+// Track centerline built from GT7Tracks dumps for distance projection.
+
+// Segments searched around the previous match before falling back to a full scan.
+const LOCAL_SEARCH_SEGMENTS: usize = 60;
+// A local match further than this from the car triggers a full scan.
+const LOCAL_MATCH_MAX_OFFSET_M: f32 = 30.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct CenterlineProjection {
+    pub distance_m: f32,
+    pub segment: usize,
+    pub offset_m: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Centerline {
+    points: Vec<(f32, f32)>,
+    cumulative_m: Vec<f32>,
+    length_m: f32,
+}
+
+impl Centerline {
+    pub fn from_points(points: Vec<(f32, f32)>) -> Option<Self> {
+        let mut deduped: Vec<(f32, f32)> = Vec::with_capacity(points.len());
+        for point in points {
+            if deduped.last() != Some(&point) {
+                deduped.push(point);
+            }
+        }
+        if deduped.len() < 2 {
+            return None;
+        }
+
+        let mut cumulative_m = Vec::with_capacity(deduped.len() + 1);
+        let mut total = 0.0f32;
+        cumulative_m.push(0.0);
+        for window in deduped.windows(2) {
+            total += segment_length(window[0], window[1]);
+            cumulative_m.push(total);
+        }
+        // Close the loop so the last point connects back to the first.
+        let first = deduped[0];
+        let last = deduped[deduped.len() - 1];
+        total += segment_length(last, first);
+        cumulative_m.push(total);
+        if total <= 0.0 {
+            return None;
+        }
+
+        Some(Self {
+            points: deduped,
+            cumulative_m,
+            length_m: total,
+        })
+    }
+
+    pub fn length_m(&self) -> f32 {
+        self.length_m
+    }
+
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn point_at(&self, distance_m: f32) -> (f32, f32) {
+        let distance_m = distance_m.rem_euclid(self.length_m);
+        let index = self
+            .cumulative_m
+            .partition_point(|value| *value <= distance_m)
+            .saturating_sub(1)
+            .min(self.segment_count() - 1);
+        let (a, b) = self.segment(index);
+        let seg_len = self.cumulative_m[index + 1] - self.cumulative_m[index];
+        let t = if seg_len > 0.0 {
+            (distance_m - self.cumulative_m[index]) / seg_len
+        } else {
+            0.0
+        };
+        (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+    }
+
+    pub fn project(&self, x: f32, z: f32, hint: Option<usize>) -> Option<CenterlineProjection> {
+        if let Some(hint) = hint {
+            let count = self.segment_count();
+            let span = LOCAL_SEARCH_SEGMENTS.min(count / 2);
+            let start = hint + count - span;
+            let local = (0..=span * 2)
+                .map(|step| (start + step) % count)
+                .filter_map(|segment| self.project_on_segment(segment, x, z))
+                .min_by(|a, b| a.offset_m.total_cmp(&b.offset_m));
+            if let Some(local) = local {
+                if local.offset_m <= LOCAL_MATCH_MAX_OFFSET_M {
+                    return Some(local);
+                }
+            }
+        }
+
+        (0..self.segment_count())
+            .filter_map(|segment| self.project_on_segment(segment, x, z))
+            .min_by(|a, b| a.offset_m.total_cmp(&b.offset_m))
+    }
+
+    fn segment(&self, index: usize) -> ((f32, f32), (f32, f32)) {
+        let a = self.points[index];
+        let b = self.points[(index + 1) % self.points.len()];
+        (a, b)
+    }
+
+    fn project_on_segment(&self, index: usize, x: f32, z: f32) -> Option<CenterlineProjection> {
+        let (a, b) = self.segment(index);
+        let (dx, dz) = (b.0 - a.0, b.1 - a.1);
+        let len_sq = dx * dx + dz * dz;
+        if len_sq <= 0.0 {
+            return None;
+        }
+        let t = (((x - a.0) * dx + (z - a.1) * dz) / len_sq).clamp(0.0, 1.0);
+        let (px, pz) = (a.0 + dx * t, a.1 + dz * t);
+        let offset_m = ((x - px).powi(2) + (z - pz).powi(2)).sqrt();
+        let seg_len = self.cumulative_m[index + 1] - self.cumulative_m[index];
+        Some(CenterlineProjection {
+            distance_m: self.cumulative_m[index] + seg_len * t,
+            segment: index,
+            offset_m,
+        })
+    }
+}
+
+fn segment_length(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
@@ -101,6 +101,12 @@ impl TrackDetector {
     }
 }
 
+impl Default for TrackDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 fn bounds_iou(min_x: f32, min_z: f32, max_x: f32, max_z: f32, other: &TrackBounds) -> f32 {
     let inter_min_x = min_x.max(other.min_x);
     let inter_max_x = max_x.min(other.max_x);
@@ -4,10 +4,12 @@ use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 use tracing::warn;
 
+use super::centerline::Centerline;
+
 const VENDOR_DIR: &str = "vendor";
 const GT7TRACKS_DIR: &str = "GT7Tracks";
 const GT7TRACKS_DUMPS_DIR: &str = "dumps";
@@ -32,6 +34,7 @@ pub struct TrackGeometryIndex {
     pub(crate) dumps_dir: PathBuf,
     pub(crate) bounds: HashMap<i32, TrackBounds>,
     pub(crate) svg_cache: Mutex<HashMap<i32, TrackSvg>>,
+    pub(crate) centerline_cache: Mutex<HashMap<i32, Arc<Centerline>>>,
 }
 
 impl TrackGeometryIndex {
@@ -42,6 +45,7 @@ impl TrackGeometryIndex {
             dumps_dir,
             bounds,
             svg_cache: Mutex::new(HashMap::new()),
+            centerline_cache: Mutex::new(HashMap::new()),
         }
     }
 
@@ -75,6 +79,25 @@ impl TrackGeometryIndex {
 
         Some(svg)
     }
+
+    pub fn cached_centerline(&self, track_id: i32) -> Option<Arc<Centerline>> {
+        self.centerline_cache.lock().ok()?.get(&track_id).cloned()
+    }
+
+    pub fn get_centerline(&self, track_id: i32) -> Option<Arc<Centerline>> {
+        if let Some(centerline) = self.cached_centerline(track_id) {
+            return Some(centerline);
+        }
+
+        let path = self.get_geometry_path(track_id)?;
+        let centerline = Arc::new(Centerline::from_points(read_track_points(&path)?)?);
+
+        if let Ok(mut cache) = self.centerline_cache.lock() {
+            cache.insert(track_id, centerline.clone());
+        }
+
+        Some(centerline)
+    }
 }
 
 fn load_track_bounds(dumps_dir: &Path) -> HashMap<i32, TrackBounds> {
@@ -221,7 +244,7 @@ pub fn build_track_svg(path: &Path) -> Option<TrackSvg> {
     })
 }
 
-fn read_track_points(path: &Path) -> Option<Vec<(f32, f32)>> {
+pub(crate) fn read_track_points(path: &Path) -> Option<Vec<(f32, f32)>> {
     let file = File::open(path).ok()?;
     let mut reader = BufReader::new(file);
     let mut line = String::new();
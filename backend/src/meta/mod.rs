@@ -1,9 +1,11 @@
 // Metadata layer for car/track names and GT7Tracks geometry discovery.
 
+mod centerline;
 mod detector;
 mod geometry;
 mod store;
 
+pub use centerline::{Centerline, CenterlineProjection};
 pub use detector::TrackDetector;
 pub use geometry::{TrackBounds, TrackSvg};
 pub use telemetry_core::packet::{parse_packet_meta, PacketMeta};
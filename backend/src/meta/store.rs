@@ -2,14 +2,17 @@
 
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use tracing::{info, warn};
 
+use super::centerline::Centerline;
 use super::geometry::{TrackBounds, TrackGeometryIndex, TrackSvg};
 
 const CARS_CSV: &str = include_str!("data/cars.csv");
 const MAKERS_CSV: &str = include_str!("data/maker.csv");
 const COURSES_CSV: &str = include_str!("data/course.csv");
+const SECTORS_FILE: &str = "sectors.csv";
 
 #[derive(Clone, Debug)]
 pub struct CarMeta {
@@ -25,12 +28,15 @@ pub struct TrackMeta {
     pub name: String,
     pub layout_number: Option<i32>,
     pub is_reverse: Option<bool>,
+    pub length_m: Option<f32>,
+    pub longest_straight_m: Option<f32>,
 }
 
 pub struct MetadataStore {
     cars: HashMap<i32, CarMeta>,
     tracks: HashMap<i32, TrackMeta>,
     tracks_by_base: HashMap<i32, i32>,
+    sectors: HashMap<i32, Vec<f32>>,
     geometry: TrackGeometryIndex,
 }
 
@@ -40,11 +46,13 @@ impl MetadataStore {
         let cars = load_cars(&makers);
         let tracks = load_tracks();
         let tracks_by_base = build_track_base_index(&tracks);
+        let sectors = load_sector_markers(&data_dir.join(SECTORS_FILE));
         let geometry = TrackGeometryIndex::load(data_dir);
 
         info!(
             car_count = cars.len(),
             track_count = tracks.len(),
+            sector_tracks = sectors.len(),
             geometry_tracks = geometry.bounds.len(),
             dumps_dir = %geometry.dumps_dir.display(),
             "metadata loaded"
@@ -54,6 +62,7 @@ impl MetadataStore {
             cars,
             tracks,
             tracks_by_base,
+            sectors,
             geometry,
         }
     }
@@ -95,6 +104,19 @@ impl MetadataStore {
     pub fn get_track_geometry_svg(&self, track_id: i32) -> Option<TrackSvg> {
         self.geometry.get_geometry_svg(track_id)
     }
+
+    pub fn get_track_sectors(&self, track_id: i32) -> Option<&[f32]> {
+        self.sectors.get(&track_id).map(Vec::as_slice)
+    }
+
+    pub fn get_track_centerline(&self, track_id: i32) -> Option<Arc<Centerline>> {
+        self.geometry.get_centerline(track_id)
+    }
+
+    // Never touches the disk, for callers holding the telemetry store lock.
+    pub fn cached_track_centerline(&self, track_id: i32) -> Option<Arc<Centerline>> {
+        self.geometry.cached_centerline(track_id)
+    }
 }
 
 fn load_makers() -> HashMap<i32, String> {
@@ -166,8 +188,10 @@ fn load_tracks() -> HashMap<i32, TrackMeta> {
         let id = parse_i32(record.get(0));
         let name = record.get(1).map(str::trim).filter(|v| !v.is_empty());
         let base_id = parse_i32(record.get(2));
-        let layout_number = parse_i32(record.get(14));
-        let is_reverse = parse_bool(record.get(15));
+        let length_m = parse_f32(record.get(5));
+        let longest_straight_m = parse_f32(record.get(6));
+        let layout_number = parse_i32(record.get(15));
+        let is_reverse = parse_bool(record.get(16));
         if let (Some(id), Some(name)) = (id, name) {
             tracks.insert(
                 id,
@@ -177,6 +201,8 @@ fn load_tracks() -> HashMap<i32, TrackMeta> {
                     name: name.to_string(),
                     layout_number,
                     is_reverse,
+                    length_m,
+                    longest_straight_m,
                 },
             );
         }
@@ -184,6 +210,47 @@ fn load_tracks() -> HashMap<i32, TrackMeta> {
     tracks
 }
 
+// Each row is `track_id,markers_m` with sector start distances separated by `;`.
+fn load_sector_markers(path: &Path) -> HashMap<i32, Vec<f32>> {
+    let mut sectors = HashMap::new();
+    if !path.is_file() {
+        return sectors;
+    }
+    let mut reader = match csv::ReaderBuilder::new().has_headers(true).from_path(path) {
+        Ok(reader) => reader,
+        Err(err) => {
+            warn!(?err, path = %path.display(), "failed to open sector markers");
+            return sectors;
+        }
+    };
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                warn!(?err, "sector csv parse failed");
+                continue;
+            }
+        };
+        let id = parse_i32(record.get(0));
+        let markers = record
+            .get(1)
+            .and_then(|value| {
+                value
+                    .split(';')
+                    .map(|marker| parse_f32(Some(marker)))
+                    .collect::<Option<Vec<f32>>>()
+            })
+            .filter(|markers| markers.windows(2).all(|pair| pair[0] < pair[1]));
+        match (id, markers) {
+            (Some(id), Some(markers)) => {
+                sectors.insert(id, markers);
+            }
+            _ => warn!(row = ?record, "invalid sector markers row"),
+        }
+    }
+    sectors
+}
+
 fn build_track_base_index(tracks: &HashMap<i32, TrackMeta>) -> HashMap<i32, i32> {
     let mut index: HashMap<i32, (i32, bool, i32)> = HashMap::new();
     for track in tracks.values() {
@@ -197,7 +264,7 @@ fn build_track_base_index(tracks: &HashMap<i32, TrackMeta>) -> HashMap<i32, i32>
         match index.get(&base_id) {
             Some(existing) => {
                 let replace = candidate.0 < existing.0
-                    || (candidate.0 == existing.0 && candidate.1 == false && existing.1 == true);
+                    || (candidate.0 == existing.0 && !candidate.1 && existing.1);
                 if replace {
                     index.insert(base_id, candidate);
                 }
@@ -214,6 +281,12 @@ fn parse_i32(value: Option<&str>) -> Option<i32> {
     value.and_then(|value| value.trim().parse::<i32>().ok())
 }
 
+fn parse_f32(value: Option<&str>) -> Option<f32> {
+    value
+        .and_then(|value| value.trim().parse::<f32>().ok())
+        .filter(|value| *value > 0.0)
+}
+
 fn parse_bool(value: Option<&str>) -> Option<bool> {
     match value.map(str::trim) {
         Some("1") => Some(true),
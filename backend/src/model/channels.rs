@@ -0,0 +1,481 @@
+// Sample channels: any numeric State field by name, plus derived channels, recorded per sample.
+
+use std::sync::{Arc, Weak};
+
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+
+use super::{Sample, State};
+use crate::constants::MAX_SAMPLE_CHANNELS;
+
+pub const DEFAULT_SAMPLE_CHANNELS: [&str; 6] = [
+    "speed_kph",
+    "rpm",
+    "throttle",
+    "brake",
+    "lap_distance_m",
+    "lap_progress",
+];
+
+type Read = fn(&State) -> Option<f32>;
+
+const DERIVED_CHANNELS: [(&str, &str, Read); 6] = [
+    ("tyre_temp_avg_c", "°C", |state| {
+        average(&[
+            state.temp_fl_c,
+            state.temp_fr_c,
+            state.temp_rl_c,
+            state.temp_rr_c,
+        ])
+    }),
+    ("tyre_slip_ratio_max", "ratio", |state| {
+        [
+            state.tyre_slip_ratio_fl,
+            state.tyre_slip_ratio_fr,
+            state.tyre_slip_ratio_rl,
+            state.tyre_slip_ratio_rr,
+        ]
+        .into_iter()
+        .flatten()
+        .map(f32::abs)
+        .reduce(f32::max)
+    }),
+    ("suspension_front", "m", |state| {
+        average(&[state.suspension_fl, state.suspension_fr])
+    }),
+    ("suspension_rear", "m", |state| {
+        average(&[state.suspension_rl, state.suspension_rr])
+    }),
+    ("pedal_overlap", "ratio", |state| {
+        Some(state.throttle?.min(state.brake?))
+    }),
+    ("fuel_fraction", "ratio", |state| {
+        match (state.fuel_l, state.fuel_capacity_l) {
+            (Some(fuel), Some(capacity)) if capacity > 0.0 => Some(fuel / capacity),
+            _ => None,
+        }
+    }),
+];
+
+trait ChannelValue {
+    fn channel_value(self) -> f32;
+}
+
+macro_rules! channel_value {
+    ($($ty:ty),*) => {
+        $(impl ChannelValue for $ty {
+            fn channel_value(self) -> f32 {
+                self as f32
+            }
+        })*
+    };
+}
+
+channel_value!(f32, i32, i16, i8, u16, u8);
+
+impl ChannelValue for bool {
+    fn channel_value(self) -> f32 {
+        if self {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+macro_rules! state_fields {
+    ($($field:ident),* $(,)?) => {
+        [$((stringify!($field), (|state: &State| state.$field.map(ChannelValue::channel_value)) as Read)),*]
+    };
+}
+
+// Every numeric or flag State field, by its serialized name.
+const STATE_FIELDS: [(&str, Read); 108] = state_fields!(
+    speed_kph,
+    rpm,
+    rpm_rev_warning,
+    rpm_rev_limiter,
+    gear,
+    gear_raw,
+    suggested_gear,
+    throttle,
+    brake,
+    clutch,
+    clutch_engaged,
+    rpm_after_clutch,
+    boost_kpa,
+    estimated_speed_kph,
+    fuel_l,
+    fuel_capacity_l,
+    oil_temp_c,
+    water_temp_c,
+    oil_pressure_kpa,
+    ride_height_mm,
+    temp_fl_c,
+    temp_fr_c,
+    temp_rl_c,
+    temp_rr_c,
+    tyre_diameter_fl_m,
+    tyre_diameter_fr_m,
+    tyre_diameter_rl_m,
+    tyre_diameter_rr_m,
+    wheel_speed_fl,
+    wheel_speed_fr,
+    wheel_speed_rl,
+    wheel_speed_rr,
+    tyre_speed_fl_kph,
+    tyre_speed_fr_kph,
+    tyre_speed_rl_kph,
+    tyre_speed_rr_kph,
+    tyre_slip_ratio_fl,
+    tyre_slip_ratio_fr,
+    tyre_slip_ratio_rl,
+    tyre_slip_ratio_rr,
+    suspension_fl,
+    suspension_fr,
+    suspension_rl,
+    suspension_rr,
+    gear_ratio_1,
+    gear_ratio_2,
+    gear_ratio_3,
+    gear_ratio_4,
+    gear_ratio_5,
+    gear_ratio_6,
+    gear_ratio_7,
+    gear_ratio_8,
+    gear_ratio_unknown,
+    vel_x,
+    vel_y,
+    vel_z,
+    angular_vel_x,
+    angular_vel_y,
+    angular_vel_z,
+    yaw_rate,
+    pitch,
+    roll,
+    rotation_extra,
+    in_race,
+    is_paused,
+    packet_id,
+    current_position,
+    total_positions,
+    current_lap,
+    total_laps,
+    best_lap_ms,
+    last_lap_ms,
+    time_on_track_ms,
+    current_lap_time_ms,
+    car_id,
+    track_id,
+    avg_fuel_consume_pct_per_lap,
+    fuel_laps_remaining,
+    personal_best_ms,
+    delta_ms,
+    predicted_lap_ms,
+    lap_distance_m,
+    lap_progress,
+    sector_index,
+    sector_delta_ms,
+    theoretical_best_ms,
+    final_drive_ratio,
+    speed_at_limiter_kph,
+    theoretical_top_speed_kph,
+    pos_x,
+    pos_y,
+    pos_z,
+    rotation_yaw,
+    flags_8e,
+    flags_8f,
+    flags_93,
+    unknown_0x94,
+    unknown_0x98,
+    unknown_0x9c,
+    unknown_0xa0,
+    unknown_0xd4,
+    unknown_0xd8,
+    unknown_0xdc,
+    unknown_0xe0,
+    unknown_0xe4,
+    unknown_0xe8,
+    unknown_0xec,
+    unknown_0xf0,
+);
+
+pub fn is_state_field(name: &str) -> bool {
+    STATE_FIELDS.iter().any(|(field, _)| *field == name)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Channel {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<&'static str>,
+    #[serde(skip)]
+    read: Read,
+}
+
+impl Channel {
+    // Resolved once, so recording reads the State field or derivation directly.
+    pub fn resolve(name: &str) -> Result<Self, String> {
+        if let Some((_, unit, read)) = DERIVED_CHANNELS
+            .iter()
+            .find(|(derived, _, _)| *derived == name)
+        {
+            return Ok(Self {
+                name: name.to_string(),
+                unit: Some(unit),
+                read: *read,
+            });
+        }
+        match STATE_FIELDS.iter().find(|(field, _)| *field == name) {
+            Some((_, read)) => Ok(Self {
+                name: name.to_string(),
+                unit: field_unit(name),
+                read: *read,
+            }),
+            None => Err(format!("unknown sample channel '{name}'")),
+        }
+    }
+
+    fn value(&self, state: &State) -> Option<f32> {
+        (self.read)(state)
+    }
+}
+
+fn average(values: &[Option<f32>]) -> Option<f32> {
+    let values: Vec<f32> = values.iter().flatten().copied().collect();
+    (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+}
+
+// Units follow the State field naming; fields without a recognisable unit have none.
+fn field_unit(name: &str) -> Option<&'static str> {
+    let unit = match name {
+        "rpm" | "rpm_after_clutch" | "rpm_rev_warning" | "rpm_rev_limiter" => "rpm",
+        "throttle" | "brake" | "clutch" | "clutch_engaged" | "lap_progress" => "ratio",
+        "yaw_rate" => "rad/s",
+        _ if name.starts_with("tyre_slip_ratio_") => "ratio",
+        _ if name.starts_with("wheel_speed_") || name.starts_with("angular_vel_") => "rad/s",
+        _ if name.starts_with("vel_") => "m/s",
+        _ if name.starts_with("pos_") || name.starts_with("suspension_") => "m",
+        _ if name.ends_with("_kph") => "km/h",
+        _ if name.ends_with("_kpa") => "kPa",
+        _ if name.ends_with("_ms") => "ms",
+        _ if name.ends_with("_mm") => "mm",
+        _ if name.ends_with("_m") => "m",
+        _ if name.ends_with("_c") => "°C",
+        _ if name.ends_with("_l") => "L",
+        _ => return None,
+    };
+    Some(unit)
+}
+
+// Keeps a recorded channel alive for a selection; the channel stops recording once the last
+// lease on it is dropped, and its slot can then be reused.
+#[derive(Clone, Debug)]
+pub struct ChannelLease(Arc<usize>);
+
+impl ChannelLease {
+    pub fn index(&self) -> usize {
+        *self.0
+    }
+}
+
+struct Slot {
+    channel: Channel,
+    lease: Weak<usize>,
+    // Time of the first sample taken for this channel; values before it belong to whichever
+    // channel held the slot earlier.
+    since_ms: Option<u64>,
+}
+
+impl Slot {
+    fn live(&self) -> bool {
+        self.lease.strong_count() > 0
+    }
+}
+
+// Every channel a live selection uses; a sample's values line up with the slots as they stood
+// when the sample was taken, so channels added later are empty in older samples.
+pub struct SampleChannels {
+    slots: Vec<Slot>,
+    defaults: Vec<ChannelLease>,
+}
+
+impl SampleChannels {
+    pub fn new() -> Self {
+        let mut channels = Self {
+            slots: Vec::new(),
+            defaults: Vec::new(),
+        };
+        let names = DEFAULT_SAMPLE_CHANNELS.map(str::to_string);
+        channels.defaults = channels.select(&names).unwrap_or_default();
+        channels
+    }
+
+    pub fn set_defaults(&mut self, names: &[String]) -> Result<(), String> {
+        self.defaults = self.select(names)?;
+        Ok(())
+    }
+
+    pub fn defaults(&self) -> Vec<usize> {
+        self.defaults.iter().map(ChannelLease::index).collect()
+    }
+
+    // Starts recording any channels not already live; nothing changes when a name is rejected.
+    pub fn select(&mut self, names: &[String]) -> Result<Vec<ChannelLease>, String> {
+        if names.is_empty() {
+            return Err("channels must not be empty".to_string());
+        }
+        let mut added: Vec<&String> = Vec::new();
+        for name in names {
+            if self.lease(name).is_none() && !added.contains(&name) {
+                Channel::resolve(name)?;
+                added.push(name);
+            }
+        }
+        let live = self.slots.iter().filter(|slot| slot.live()).count();
+        if live + added.len() > MAX_SAMPLE_CHANNELS {
+            return Err(format!(
+                "at most {MAX_SAMPLE_CHANNELS} sample channels can be recorded"
+            ));
+        }
+        let mut leases = Vec::with_capacity(names.len());
+        for name in names {
+            let lease = match self.lease(name) {
+                Some(lease) => lease,
+                None => self.insert(Channel::resolve(name)?),
+            };
+            leases.push(lease);
+        }
+        Ok(leases)
+    }
+
+    fn lease(&self, name: &str) -> Option<ChannelLease> {
+        self.slots
+            .iter()
+            .filter(|slot| slot.channel.name == name)
+            .find_map(|slot| slot.lease.upgrade())
+            .map(ChannelLease)
+    }
+
+    fn insert(&mut self, channel: Channel) -> ChannelLease {
+        let index = self
+            .slots
+            .iter()
+            .position(|slot| !slot.live())
+            .unwrap_or(self.slots.len());
+        let lease = Arc::new(index);
+        let slot = Slot {
+            channel,
+            lease: Arc::downgrade(&lease),
+            since_ms: None,
+        };
+        if index == self.slots.len() {
+            self.slots.push(slot);
+        } else {
+            self.slots[index] = slot;
+        }
+        ChannelLease(lease)
+    }
+
+    pub fn record(&mut self, t_ms: u64, state: &State) -> Sample {
+        Sample {
+            t_ms,
+            values: self
+                .slots
+                .iter_mut()
+                .map(|slot| {
+                    if !slot.live() {
+                        return None;
+                    }
+                    slot.since_ms.get_or_insert(t_ms);
+                    slot.channel.value(state)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn project<'a>(
+        &self,
+        selection: &[usize],
+        samples: impl IntoIterator<Item = &'a Sample>,
+    ) -> ChannelSamples {
+        ChannelSamples {
+            channels: selection
+                .iter()
+                .map(|index| self.slots[*index].channel.clone())
+                .collect(),
+            samples: samples
+                .into_iter()
+                .map(|sample| Sample {
+                    t_ms: sample.t_ms,
+                    values: selection
+                        .iter()
+                        .map(|index| {
+                            let since_ms = self.slots[*index].since_ms?;
+                            (sample.t_ms >= since_ms)
+                                .then(|| sample.values.get(*index).copied().flatten())
+                                .flatten()
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Default for SampleChannels {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Samples projected onto a channel selection. Each sample serializes as `t_ms` plus one
+// `name: value` pair per channel that has a value.
+pub struct ChannelSamples {
+    pub channels: Vec<Channel>,
+    pub samples: Vec<Sample>,
+}
+
+struct Rows<'a>(&'a ChannelSamples);
+
+struct Row<'a> {
+    channels: &'a [Channel],
+    sample: &'a Sample,
+}
+
+impl Serialize for ChannelSamples {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("channels", &self.channels)?;
+        map.serialize_entry("samples", &Rows(self))?;
+        map.end()
+    }
+}
+
+impl Serialize for Rows<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.samples.len()))?;
+        for sample in &self.0.samples {
+            seq.serialize_element(&Row {
+                channels: &self.0.channels,
+                sample,
+            })?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for Row<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = 1 + self.sample.values.iter().flatten().count();
+        let mut map = serializer.serialize_map(Some(len))?;
+        map.serialize_entry("t_ms", &self.sample.t_ms)?;
+        for (channel, value) in self.channels.iter().zip(&self.sample.values) {
+            if let Some(value) = value {
+                map.serialize_entry(&channel.name, value)?;
+            }
+        }
+        map.end()
+    }
+}
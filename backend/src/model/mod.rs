@@ -1,3 +1,7 @@
+mod channels;
 mod sample;
+pub use channels::{
+    is_state_field, Channel, ChannelLease, ChannelSamples, SampleChannels, DEFAULT_SAMPLE_CHANNELS,
+};
 pub use sample::Sample;
 pub use telemetry_core::model::{State, TelemetryFrame};
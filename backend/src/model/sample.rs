@@ -1,16 +1,8 @@
 // Downsampled telemetry sample used in rolling windows.
+// Values line up with the recorded sample channels; see channels.rs.
 
-use serde::Serialize;
-
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug)]
 pub struct Sample {
     pub t_ms: u64,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub speed_kph: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub rpm: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub throttle: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub brake: Option<f32>,
+    pub values: Vec<Option<f32>>,
 }
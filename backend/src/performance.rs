@@ -0,0 +1,134 @@
+// Acceleration and braking test results persisted per car.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::analysis::{PerformanceKind, PerformanceResult};
+use crate::constants::{PERFORMANCE_FILE, RECORDS_DIR};
+use crate::utils::{JsonFile, JsonSnapshot};
+
+const RESULTS_PER_CAR_CAP: usize = 100;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PerformanceRecord {
+    pub car_id: i32,
+    pub recorded_at_ms: u64,
+    #[serde(flatten)]
+    pub result: PerformanceResult,
+}
+
+pub struct PerformanceUpdate {
+    pub record: PerformanceRecord,
+    pub previous_best_ms: Option<u32>,
+}
+
+impl PerformanceUpdate {
+    pub fn is_best(&self) -> bool {
+        self.previous_best_ms
+            .is_none_or(|best| self.record.result.time_ms < best)
+    }
+}
+
+pub struct PerformanceStore {
+    file: JsonFile,
+    results: HashMap<i32, Vec<PerformanceRecord>>,
+}
+
+impl PerformanceStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(RECORDS_DIR).join(PERFORMANCE_FILE);
+        let mut results: HashMap<i32, Vec<PerformanceRecord>> = HashMap::new();
+        match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<Vec<PerformanceRecord>>(&bytes) {
+                Ok(list) => {
+                    for record in list {
+                        results.entry(record.car_id).or_default().push(record);
+                    }
+                }
+                Err(err) => warn!(?err, path = %path.display(), "performance file parse failed"),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => warn!(?err, path = %path.display(), "performance file read failed"),
+        }
+        info!(cars = results.len(), path = %path.display(), "performance tests loaded");
+        Self {
+            file: JsonFile::new(path),
+            results,
+        }
+    }
+
+    pub fn car_ids(&self) -> Vec<i32> {
+        let mut ids: Vec<i32> = self.results.keys().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    // Oldest first.
+    pub fn results(&self, car_id: i32) -> &[PerformanceRecord] {
+        self.results.get(&car_id).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn best(&self, car_id: i32, kind: PerformanceKind) -> Option<&PerformanceRecord> {
+        self.results(car_id)
+            .iter()
+            .filter(|record| record.result.kind == kind)
+            .min_by_key(|record| record.result.time_ms)
+    }
+
+    pub fn offer(
+        &mut self,
+        car_id: i32,
+        result: PerformanceResult,
+        recorded_at_ms: u64,
+    ) -> PerformanceUpdate {
+        let previous_best_ms = self
+            .best(car_id, result.kind)
+            .map(|record| record.result.time_ms);
+        let record = PerformanceRecord {
+            car_id,
+            recorded_at_ms,
+            result,
+        };
+        let results = self.results.entry(car_id).or_default();
+        // Keep the per-kind bests when trimming the history.
+        if results.len() >= RESULTS_PER_CAR_CAP {
+            let bests: Vec<usize> = PerformanceKind::ALL
+                .iter()
+                .filter_map(|kind| {
+                    results
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, record)| record.result.kind == *kind)
+                        .min_by_key(|(_, record)| record.result.time_ms)
+                        .map(|(idx, _)| idx)
+                })
+                .collect();
+            if let Some(idx) = (0..results.len()).find(|idx| !bests.contains(idx)) {
+                results.remove(idx);
+            }
+        }
+        results.push(record.clone());
+        PerformanceUpdate {
+            record,
+            previous_best_ms,
+        }
+    }
+
+    pub fn snapshot(&self) -> JsonSnapshot<Vec<PerformanceRecord>> {
+        let records = self
+            .car_ids()
+            .into_iter()
+            .flat_map(|car_id| self.results(car_id).iter().cloned())
+            .collect();
+        self.file.snapshot(records)
+    }
+}
+
+pub async fn save_performance(snapshot: JsonSnapshot<Vec<PerformanceRecord>>) {
+    if let Err(err) = snapshot.save().await {
+        warn!(?err, path = %snapshot.path().display(), "failed to save performance tests");
+    }
+}
@@ -7,6 +7,8 @@ use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 
 use crate::app::{RecordMode, RecordState};
+use crate::events::Event;
+use crate::ws::Broadcaster;
 
 #[derive(Serialize)]
 pub struct RecordStatusResponse {
@@ -32,6 +34,7 @@ pub fn record_status_snapshot(state: &RecordState) -> RecordStatusResponse {
 
 pub async fn stop_recording_internal(
     record_state: &Arc<Mutex<RecordState>>,
+    broadcaster: &Broadcaster,
 ) -> RecordStatusResponse {
     let mut state = record_state.lock().await;
     if state.mode != RecordMode::Recording {
@@ -43,10 +46,19 @@ pub async fn stop_recording_internal(
     state.mode = RecordMode::Idle;
     state.writer = None;
     state.start_ms = None;
-    record_status_snapshot(&state)
+    let status = record_status_snapshot(&state);
+    broadcaster.event(Event::RecordingStopped {
+        path: status.path.clone().unwrap_or_default(),
+        frames: status.frames,
+    });
+    status
 }
 
-pub async fn maybe_start_recording(record_state: &Arc<Mutex<RecordState>>, now_ms: u64) {
+pub async fn maybe_start_recording(
+    record_state: &Arc<Mutex<RecordState>>,
+    now_ms: u64,
+    broadcaster: &Broadcaster,
+) {
     let path = {
         let state = record_state.lock().await;
         if state.mode != RecordMode::Armed {
@@ -83,6 +95,9 @@ pub async fn maybe_start_recording(record_state: &Arc<Mutex<RecordState>>, now_m
     state.start_ms = Some(now_ms);
     state.frames = 0;
     state.mode = RecordMode::Recording;
+    broadcaster.event(Event::RecordingStarted {
+        path: path.to_string_lossy().to_string(),
+    });
 }
 
 pub async fn record_raw_packet(record_state: &Arc<Mutex<RecordState>>, now_ms: u64, encrypted: &[u8]) {
@@ -0,0 +1,177 @@
+// Personal best lap records persisted per car/track combination.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::analysis::{LapPoint, LapTrace};
+use crate::constants::{PERSONAL_BEST_FILE, RECORDS_DIR};
+use crate::meta::MetadataStore;
+use crate::utils::{JsonFile, JsonSnapshot};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PersonalBest {
+    pub car_id: i32,
+    pub track_id: i32,
+    pub base_track_id: Option<i32>,
+    pub layout_number: Option<i32>,
+    pub lap_time_ms: i32,
+    pub set_at_ms: u64,
+    pub samples: Vec<LapPoint>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PersonalBestSummary {
+    pub car_id: i32,
+    pub track_id: i32,
+    pub base_track_id: Option<i32>,
+    pub layout_number: Option<i32>,
+    pub lap_time_ms: i32,
+    pub set_at_ms: u64,
+    pub samples_count: usize,
+}
+
+impl PersonalBest {
+    pub fn summary(&self) -> PersonalBestSummary {
+        PersonalBestSummary {
+            car_id: self.car_id,
+            track_id: self.track_id,
+            base_track_id: self.base_track_id,
+            layout_number: self.layout_number,
+            lap_time_ms: self.lap_time_ms,
+            set_at_ms: self.set_at_ms,
+            samples_count: self.samples.len(),
+        }
+    }
+}
+
+pub struct PersonalBestUpdate {
+    pub record: PersonalBest,
+    pub previous_lap_time_ms: Option<i32>,
+}
+
+pub struct PersonalBestStore {
+    file: JsonFile,
+    records: HashMap<(i32, i32), PersonalBest>,
+}
+
+impl PersonalBestStore {
+    pub fn load(data_dir: &Path) -> Self {
+        let path = data_dir.join(RECORDS_DIR).join(PERSONAL_BEST_FILE);
+        let mut records = HashMap::new();
+        match std::fs::read(&path) {
+            Ok(bytes) => match serde_json::from_slice::<Vec<PersonalBest>>(&bytes) {
+                Ok(list) => {
+                    for record in list {
+                        records.insert((record.car_id, record.track_id), record);
+                    }
+                }
+                Err(err) => warn!(?err, path = %path.display(), "personal best file parse failed"),
+            },
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => warn!(?err, path = %path.display(), "personal best file read failed"),
+        }
+        info!(count = records.len(), path = %path.display(), "personal bests loaded");
+        Self {
+            file: JsonFile::new(path),
+            records,
+        }
+    }
+
+    pub fn get(&self, car_id: i32, track_id: i32) -> Option<&PersonalBest> {
+        self.records.get(&(car_id, track_id))
+    }
+
+    pub fn list(&self) -> Vec<&PersonalBest> {
+        let mut list: Vec<&PersonalBest> = self.records.values().collect();
+        list.sort_by_key(|record| (record.track_id, record.car_id));
+        list
+    }
+
+    pub fn offer(&mut self, lap: &LapTrace, meta: &MetadataStore) -> Option<PersonalBestUpdate> {
+        let (car_id, track_id) = (lap.car_id?, lap.track_id?);
+        // Out-laps and laps joined mid-way have short times but never covered the whole track.
+        if lap.partial || lap.lap_time_ms <= 0 {
+            return None;
+        }
+        let previous_lap_time_ms = self.get(car_id, track_id).map(|record| record.lap_time_ms);
+        if matches!(previous_lap_time_ms, Some(previous) if previous <= lap.lap_time_ms) {
+            return None;
+        }
+
+        let track = meta.get_track_info(track_id);
+        let record = PersonalBest {
+            car_id,
+            track_id,
+            base_track_id: track.and_then(|track| track.base_id),
+            layout_number: track.and_then(|track| track.layout_number),
+            lap_time_ms: lap.lap_time_ms,
+            set_at_ms: lap.completed_at_ms,
+            samples: lap.points.clone(),
+        };
+        self.records.insert((car_id, track_id), record.clone());
+        Some(PersonalBestUpdate {
+            record,
+            previous_lap_time_ms,
+        })
+    }
+
+    pub fn snapshot(&self) -> JsonSnapshot<Vec<PersonalBest>> {
+        let records = self.list().into_iter().cloned().collect();
+        self.file.snapshot(records)
+    }
+}
+
+pub async fn save_personal_bests(snapshot: JsonSnapshot<Vec<PersonalBest>>) {
+    if let Err(err) = snapshot.save().await {
+        warn!(?err, path = %snapshot.path().display(), "failed to save personal bests");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lap(lap_time_ms: i32, partial: bool) -> LapTrace {
+        LapTrace {
+            id: 1,
+            session_index: 1,
+            lap_number: 1,
+            lap_time_ms,
+            car_id: Some(3289),
+            track_id: Some(351),
+            completed_at_ms: 0,
+            partial,
+            paused: false,
+            fuel_used_l: None,
+            distance_m: 0.0,
+            points: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn partial_laps_never_become_personal_best() {
+        let data_dir = std::env::temp_dir().join("apextelemetry-records-test");
+        let meta = MetadataStore::load(&data_dir);
+        let mut store = PersonalBestStore {
+            file: JsonFile::new(data_dir.join(PERSONAL_BEST_FILE)),
+            records: HashMap::new(),
+        };
+
+        assert!(store.offer(&lap(45_000, true), &meta).is_none());
+        assert!(store.get(3289, 351).is_none());
+
+        let update = store
+            .offer(&lap(90_000, false), &meta)
+            .expect("full lap sets a record");
+        assert_eq!(update.previous_lap_time_ms, None);
+
+        assert!(store.offer(&lap(30_000, true), &meta).is_none());
+        assert_eq!(
+            store.get(3289, 351).map(|record| record.lap_time_ms),
+            Some(90_000)
+        );
+    }
+}
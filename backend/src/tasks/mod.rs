@@ -1,32 +1,32 @@
-// Background tasks for websocket updates, samples, and heartbeat.
+// Background tasks for websocket updates, samples, link status, and heartbeat.
 
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use std::time::Duration;
 
-use tokio::sync::{broadcast, watch, RwLock};
+use tokio::sync::{watch, RwLock};
 use tokio::time::{self, Instant};
 use tracing::{info, warn};
 
-use crate::app::{DetectStatus, DetectStore, HeartbeatMode, SessionState, TelemetryStore, UdpConfig};
+use crate::app::{DetectStatus, DetectStore, HeartbeatMode, TelemetryStore, UdpConfig};
 use crate::constants::{
-    HEARTBEAT_BYTE, HEARTBEAT_INTERVAL_SECS, HEARTBEAT_PORT, SCHEMA_VERSION, STATE_INTERVAL_MS,
-    WINDOW_DURATION_MS, WINDOW_INTERVAL_MS, WINDOW_STRIDE_MS,
+    HEARTBEAT_BYTE, HEARTBEAT_INTERVAL_SECS, HEARTBEAT_PORT, LINK_CHECK_INTERVAL_MS,
+    LINK_STATUS_INTERVAL_MS, SCHEMA_VERSION, STATE_INTERVAL_MS, WINDOW_INTERVAL_MS,
 };
+use crate::link::LinkState;
 use crate::net::{
     bind_heartbeat_socket, fallback_local_ip, resolve_broadcast_bind_ip, resolve_local_ip_for_target,
 };
-use crate::utils::{monotonic_ms, next_sequence, now_epoch_ms};
-use crate::ws::{SamplesWindow, SamplesWindowMessage, StateUpdateMessage};
+use crate::utils::{monotonic_ms, now_epoch_ms};
+use crate::ws::{
+    json_map, Broadcaster, DeltaEncoder, LinkStatusMessage, SamplesWindow, SamplesWindowMessage,
+    StateDeltaMessage, StateUpdateMessage,
+};
 
-pub async fn state_update_task(
-    store: Arc<RwLock<TelemetryStore>>,
-    tx: broadcast::Sender<String>,
-    sequence: Arc<AtomicU64>,
-    start: Instant,
-) {
+pub async fn state_update_task(store: Arc<RwLock<TelemetryStore>>, broadcaster: Broadcaster) {
     let mut interval = time::interval(Duration::from_millis(STATE_INTERVAL_MS));
+    // Baseline for the shared state_delta stream.
+    let mut state_delta = DeltaEncoder::new();
     loop {
         interval.tick().await;
         let (state, source_timestamp_ms) = {
@@ -38,84 +38,111 @@ pub async fn state_update_task(
             continue;
         }
 
-        let message = StateUpdateMessage {
+        let timestamp_ms = now_epoch_ms();
+        let now_ms = broadcaster.monotonic_ms();
+        let delta_state = broadcaster
+            .state_delta
+            .has_clients()
+            .then(|| json_map(&state));
+        broadcaster.send(|sequence| StateUpdateMessage {
             schema_version: SCHEMA_VERSION,
-            timestamp_ms: now_epoch_ms(),
-            monotonic_ms: monotonic_ms(start),
-            sequence: next_sequence(sequence.as_ref()),
+            timestamp_ms,
+            monotonic_ms: now_ms,
+            sequence,
             message_type: "state_update",
             state,
             source_timestamp_ms,
-        };
+        });
 
-        if let Ok(payload) = serde_json::to_string(&message) {
-            let _ = tx.send(payload);
-        }
+        // Delta clients get their own stream; nothing is sent when no field changed. Without any,
+        // the next one to join starts the chain again from a fresh keyframe.
+        let Some(delta_state) = delta_state else {
+            if state_delta.keyframe().is_some() {
+                state_delta.reset();
+                broadcaster.state_delta.publish(&state_delta);
+            }
+            continue;
+        };
+        // The baseline is published before the delta goes out, so a client joining in between
+        // gets keyframe N and drops broadcast delta N rather than missing it.
+        broadcaster.send_with(|sequence| {
+            let delta = state_delta.next(sequence, delta_state, now_ms)?;
+            broadcaster.state_delta.publish(&state_delta);
+            Some(StateDeltaMessage {
+                schema_version: SCHEMA_VERSION,
+                timestamp_ms,
+                monotonic_ms: now_ms,
+                sequence,
+                message_type: "state_delta",
+                request_id: None,
+                keyframe: delta.base_sequence.is_none(),
+                base_sequence: delta.base_sequence,
+                state: delta.state,
+                removed: delta.removed,
+                source_timestamp_ms,
+            })
+        });
     }
 }
 
-pub async fn samples_window_task(
-    store: Arc<RwLock<TelemetryStore>>,
-    tx: broadcast::Sender<String>,
-    sequence: Arc<AtomicU64>,
-    start: Instant,
-) {
+pub async fn samples_window_task(store: Arc<RwLock<TelemetryStore>>, broadcaster: Broadcaster) {
     let mut interval = time::interval(Duration::from_millis(WINDOW_INTERVAL_MS));
     loop {
         interval.tick().await;
-        let now_ms = monotonic_ms(start);
-        let start_ms = now_ms.saturating_sub(WINDOW_DURATION_MS);
-        let samples = {
+        let window = {
             let store = store.read().await;
-            if store.session.session_state != SessionState::InRace {
-                continue;
-            }
-            if store.samples.len() == 0 {
-                continue;
-            }
-            store.samples.to_vec_ordered()
+            let selection = store.sample_channels.defaults();
+            SamplesWindow::build(&store, &selection, broadcaster.monotonic_ms())
         };
-
-        let mut window_samples = Vec::new();
-        let mut last_t = None;
-        for sample in samples {
-            if sample.t_ms < start_ms || sample.t_ms > now_ms {
-                continue;
-            }
-            let emit = match last_t {
-                Some(prev) => sample.t_ms.saturating_sub(prev) >= WINDOW_STRIDE_MS,
-                None => true,
-            };
-            if emit {
-                last_t = Some(sample.t_ms);
-                window_samples.push(sample);
-            }
-        }
-
-        if window_samples.is_empty() {
+        let Some(window) = window else {
             continue;
-        }
-
-        let window = SamplesWindow {
-            start_ms,
-            end_ms: now_ms,
-            stride_ms: WINDOW_STRIDE_MS,
-            samples: window_samples,
         };
 
-        let message = SamplesWindowMessage {
+        broadcaster.send(|sequence| SamplesWindowMessage {
             schema_version: SCHEMA_VERSION,
             timestamp_ms: now_epoch_ms(),
-            monotonic_ms: monotonic_ms(start),
-            sequence: next_sequence(sequence.as_ref()),
+            monotonic_ms: broadcaster.monotonic_ms(),
+            sequence,
             message_type: "samples_window",
             window,
             decimated: true,
-        };
+        });
+    }
+}
 
-        if let Ok(payload) = serde_json::to_string(&message) {
-            let _ = tx.send(payload);
+// Re-evaluates the link every check and pushes its status on each change and once a second.
+pub async fn link_status_task(store: Arc<RwLock<TelemetryStore>>, broadcaster: Broadcaster) {
+    let mut interval = time::interval(Duration::from_millis(LINK_CHECK_INTERVAL_MS));
+    let mut last_sent_ms: Option<u64> = None;
+    loop {
+        interval.tick().await;
+        let now_ms = broadcaster.monotonic_ms();
+        let (changed, link) = {
+            let mut store = store.write().await;
+            let changed = store.link.update(now_ms);
+            (changed, store.link.status(now_ms))
+        };
+        if changed {
+            if matches!(link.state, LinkState::Stale | LinkState::Lost) {
+                warn!(state = ?link.state, age_ms = link.last_packet_age_ms, "telemetry link degraded");
+            } else {
+                info!(state = ?link.state, heartbeat_mode = link.heartbeat_mode, "telemetry link changed");
+            }
         }
+        let due = last_sent_ms
+            .is_none_or(|sent_ms| now_ms.saturating_sub(sent_ms) >= LINK_STATUS_INTERVAL_MS);
+        if !changed && !due {
+            continue;
+        }
+        last_sent_ms = Some(now_ms);
+        broadcaster.send(|sequence| LinkStatusMessage {
+            schema_version: SCHEMA_VERSION,
+            timestamp_ms: now_epoch_ms(),
+            monotonic_ms: now_ms,
+            sequence,
+            message_type: "link_status",
+            link,
+        });
     }
 }
 
@@ -132,7 +159,6 @@ pub async fn heartbeat_task(
     let mut current_mode = HeartbeatMode::Stop;
     let mut config = config_rx.borrow().clone();
     let mut last_ps5_ip = config.ps5_ip;
-    let mut last_warn_ms: Option<u64> = None;
     let mut interval = time::interval(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
 
     loop {
@@ -141,7 +167,6 @@ pub async fn heartbeat_task(
                 apply_heartbeat_mode(
                     &mut current_mode,
                     &mut last_ps5_ip,
-                    &mut last_warn_ms,
                     &mut current_bind_ip,
                     &mut socket,
                     &config,
@@ -160,7 +185,6 @@ pub async fn heartbeat_task(
                 apply_heartbeat_mode(
                     &mut current_mode,
                     &mut last_ps5_ip,
-                    &mut last_warn_ms,
                     &mut current_bind_ip,
                     &mut socket,
                     &config,
@@ -177,10 +201,10 @@ pub async fn heartbeat_task(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn apply_heartbeat_mode(
     current_mode: &mut HeartbeatMode,
     last_ps5_ip: &mut Option<IpAddr>,
-    last_warn_ms: &mut Option<u64>,
     current_bind_ip: &mut IpAddr,
     heartbeat_socket: &mut tokio::net::UdpSocket,
     config: &UdpConfig,
@@ -226,6 +250,11 @@ async fn apply_heartbeat_mode(
     } else if !matches!(next_mode, HeartbeatMode::Stop) {
         warn!("heartbeat bind ip unavailable; stopping heartbeat");
         *current_mode = HeartbeatMode::Stop;
+        store
+            .write()
+            .await
+            .link
+            .set_mode(HeartbeatMode::Stop, monotonic_ms(start));
         return Ok(());
     }
 
@@ -268,6 +297,11 @@ async fn apply_heartbeat_mode(
                 let _ = heartbeat_socket.set_broadcast(false);
             }
         }
+        store
+            .write()
+            .await
+            .link
+            .set_mode(next_mode.clone(), monotonic_ms(start));
         *current_mode = next_mode;
     }
 
@@ -279,23 +313,6 @@ async fn apply_heartbeat_mode(
         };
         info!(local_addr = %heartbeat_socket.local_addr()?, target = %target, "heartbeat send");
         let _ = heartbeat_socket.send_to(&[HEARTBEAT_BYTE], target).await;
-
-        if let HeartbeatMode::Unicast(_) = current_mode {
-            let now_ms = monotonic_ms(start);
-            let last_ms = store.read().await.last_telemetry_ms;
-            if let Some(last_ms) = last_ms {
-                if now_ms.saturating_sub(last_ms) >= 5_000 {
-                    let should_warn = match last_warn_ms {
-                        Some(prev) => now_ms.saturating_sub(*prev) >= 5_000,
-                        None => true,
-                    };
-                    if should_warn {
-                        warn!(age_ms = now_ms.saturating_sub(last_ms), "telemetry stale while heartbeat unicast");
-                        *last_warn_ms = Some(now_ms);
-                    }
-                }
-            }
-        }
     }
 
     *last_ps5_ip = config.ps5_ip;
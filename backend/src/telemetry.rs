@@ -5,27 +5,72 @@ use std::sync::Arc;
 use tokio::sync::{Mutex, RwLock};
 use tracing::info;
 
+use crate::analysis::{TyreEvent, TyreTemperatureAlert};
 use crate::app::{PacketInfo, RecordState, SessionState, TelemetryStore};
-use crate::constants::RAW_PACKET_HISTORY;
+use crate::constants::{LAP_HISTORY_CAP, RAW_PACKET_HISTORY, SCHEMA_VERSION};
+use crate::dyno::{save_dyno, DynoStore};
+use crate::events::Event;
 use crate::meta::{MetadataStore, PacketMeta, TrackDetector};
-use crate::model::{Sample, TelemetryFrame};
+use crate::model::TelemetryFrame;
+use crate::performance::{save_performance, PerformanceStore, PerformanceUpdate};
 use crate::recording::{maybe_start_recording, stop_recording_internal};
+use crate::records::{save_personal_bests, PersonalBestStore, PersonalBestUpdate};
+use crate::udp::FrameFlags;
+use crate::utils::now_epoch_ms;
+use crate::ws::{
+    Broadcaster, PerformanceTestMessage, PersonalBestMessage, TyreEventMessage,
+    TyreTemperatureAlertMessage,
+};
 
+// Everything a frame is applied to; demo playback leaves out the stores it must not touch.
+pub struct FrameContext<'a> {
+    pub store: &'a Arc<RwLock<TelemetryStore>>,
+    pub meta: &'a Arc<MetadataStore>,
+    pub broadcaster: &'a Broadcaster,
+    pub record_state: Option<&'a Arc<Mutex<RecordState>>>,
+    pub personal_bests: Option<&'a Arc<RwLock<PersonalBestStore>>>,
+    pub dyno: Option<&'a Arc<RwLock<DynoStore>>>,
+    pub performance: Option<&'a Arc<RwLock<PerformanceStore>>>,
+}
+
+// Work decided under the store lock and carried out after it is released.
+struct FrameOutcome {
+    session_index: u64,
+    should_stop_record: bool,
+    should_start_record: bool,
+    personal_best_update: Option<PersonalBestUpdate>,
+    tyre_events: Vec<TyreEvent>,
+    temperature_alerts: Vec<TyreTemperatureAlert>,
+    flush_dyno: bool,
+    performance_updates: Vec<PerformanceUpdate>,
+    centerline_needed: Option<i32>,
+}
+
+// Returns the session index the frame was applied to.
 pub async fn apply_frame(
-    store: &Arc<RwLock<TelemetryStore>>,
-    meta: &Arc<MetadataStore>,
+    context: &FrameContext<'_>,
     track_detector: &mut TrackDetector,
     frame: &TelemetryFrame,
     packet_meta: &PacketMeta,
     now_ms: u64,
+    frame_flags: FrameFlags,
     packet_info: Option<PacketInfo>,
-    record_state: Option<&Arc<Mutex<RecordState>>>,
-) {
-    let (should_stop_record, should_start_record) = {
+) -> u64 {
+    let FrameContext {
+        store,
+        meta,
+        broadcaster,
+        record_state,
+        personal_bests,
+        dyno,
+        performance,
+    } = *context;
+    let outcome = {
         let mut store = store.write().await;
         store.last_telemetry_ms = Some(now_ms);
 
         if let Some(info) = packet_info {
+            store.link.record_packet(now_ms);
             if let Some(packet_len) = info.packet_len {
                 store.last_packet_len = Some(packet_len);
             }
@@ -44,13 +89,33 @@ pub async fn apply_frame(
         }
 
         let events = store.session.apply_frame(frame, now_ms, packet_meta.car_id);
+        let mut flush_dyno = events.lap_completed.is_some();
+
+        // Compared with the last car ever seen, since the session forgets its car between races.
+        if let Some(car_id) = store
+            .session
+            .car_id
+            .filter(|car_id| store.last_car_id != Some(*car_id))
+        {
+            let previous_car_id = store.last_car_id.replace(car_id);
+            info!(car_id, ?previous_car_id, "car changed");
+            broadcaster.event(Event::CarChanged {
+                car_id,
+                previous_car_id,
+                name: meta.get_car_name(car_id).map(str::to_string),
+            });
+        }
 
         if let Some(transition) = events.transition {
             if transition.to == SessionState::InRace && transition.from == SessionState::NotInRace {
                 store.samples.clear();
                 store.last_packet_id = None;
+                store.laps.reset();
                 track_detector.reset();
             } else if transition.to == SessionState::NotInRace {
+                flush_dyno = true;
+                store.archive_session_report(meta);
+                store.laps.reset();
                 track_detector.reset();
             }
             info!(
@@ -59,6 +124,11 @@ pub async fn apply_frame(
                 session_index = store.session.session_index,
                 "session transition"
             );
+            broadcaster.event(Event::SessionTransition {
+                from: transition.from,
+                to: transition.to,
+                session_index: store.session.session_index,
+            });
         }
 
         let track_id = track_detector.update(
@@ -68,8 +138,105 @@ pub async fn apply_frame(
             packet_meta.position_xz,
             meta.track_bounds(),
         );
+        if let Some(track_id) =
+            track_id.filter(|track_id| store.session.track_id != Some(*track_id))
+        {
+            info!(track_id, "track detected");
+            broadcaster.event(Event::TrackDetected {
+                track_id,
+                name: meta.get_track_name(track_id).map(str::to_string),
+            });
+        }
         store.session.set_track_id(track_id);
 
+        let mut personal_best_update = None;
+        let lap_update = {
+            let store = &mut *store;
+            store.laps.update(
+                &mut store.session,
+                events.lap_completed,
+                meta,
+                now_epoch_ms(),
+            )
+        };
+        if let (Some(sample), Some(dyno), Some(car_id)) =
+            (lap_update.dyno_sample, dyno, store.session.car_id)
+        {
+            dyno.write().await.record(car_id, sample, now_epoch_ms());
+        }
+        let mut performance_updates = Vec::new();
+        if let (Some(performance), Some(car_id)) = (performance, store.session.car_id) {
+            if !lap_update.performance_results.is_empty() {
+                let mut performance = performance.write().await;
+                for result in lap_update.performance_results {
+                    performance_updates.push(performance.offer(car_id, result, now_epoch_ms()));
+                }
+            }
+        }
+        if let Some(lap) = lap_update.lap {
+            info!(
+                lap_number = lap.lap_number,
+                lap_time_ms = lap.lap_time_ms,
+                partial = lap.partial,
+                "lap completed"
+            );
+            broadcaster.event(Event::LapCompleted {
+                session_index: lap.session_index,
+                lap_number: lap.lap_number,
+                lap_time_ms: lap.lap_time_ms,
+                partial: lap.partial,
+                car_id: lap.car_id,
+                track_id: lap.track_id,
+            });
+            if let Some(personal_bests) = personal_bests {
+                personal_best_update = personal_bests.write().await.offer(&lap, meta);
+            }
+            if store.lap_history.len() >= LAP_HISTORY_CAP {
+                store.lap_history.pop_front();
+            }
+            store.lap_history.push_back(Arc::new(lap));
+        }
+
+        let personal_bests_guard = match personal_bests {
+            Some(personal_bests) => Some(personal_bests.read().await),
+            None => None,
+        };
+        let personal_best = match (
+            personal_bests_guard.as_ref(),
+            store.session.car_id,
+            store.session.track_id,
+        ) {
+            (Some(guard), Some(car_id), Some(track_id)) => guard.get(car_id, track_id),
+            _ => None,
+        };
+
+        {
+            let store = &mut *store;
+            let in_race = store.session.session_state == SessionState::InRace;
+            store.delta.refresh(
+                &store.lap_history,
+                personal_best.map(|record| (record.lap_time_ms, record.samples.as_slice())),
+                store.session.session_index,
+                store.session.car_id,
+                store.session.track_id,
+            );
+            let (delta_ms, predicted_lap_ms) = if in_race {
+                match store.session.state.lap_distance_m {
+                    Some(distance_m) => store
+                        .delta
+                        .compute(distance_m, store.session.state.current_lap_time_ms),
+                    None => (None, None),
+                }
+            } else {
+                (None, None)
+            };
+            let state = &mut store.session.state;
+            state.personal_best_ms = personal_best.map(|record| record.lap_time_ms);
+            state.delta_ms = delta_ms;
+            state.predicted_lap_ms = predicted_lap_ms;
+        }
+        drop(personal_bests_guard);
+
         if store.session.session_state == SessionState::InRace {
             let allow_sample = match frame.packet_id {
                 Some(packet_id) => {
@@ -89,13 +256,8 @@ pub async fn apply_frame(
             };
 
             if allow_sample {
-                let sample = Sample {
-                    t_ms: now_ms,
-                    speed_kph: frame.speed_kph,
-                    rpm: frame.rpm,
-                    throttle: frame.throttle,
-                    brake: frame.brake,
-                };
+                let store = &mut *store;
+                let sample = store.sample_channels.record(now_ms, &store.session.state);
                 store.samples.push(sample);
             }
         }
@@ -103,18 +265,146 @@ pub async fn apply_frame(
         if frame.source_timestamp_ms.is_some() {
             store.last_source_timestamp_ms = frame.source_timestamp_ms;
         }
-        (events.should_stop_record, events.should_start_record)
+        FrameOutcome {
+            session_index: store.session.session_index,
+            should_stop_record: events.should_stop_record,
+            should_start_record: events.should_start_record,
+            personal_best_update,
+            tyre_events: lap_update.tyre_events,
+            temperature_alerts: lap_update.temperature_alerts,
+            flush_dyno,
+            performance_updates,
+            centerline_needed: lap_update.centerline_needed,
+        }
     };
 
-    if should_stop_record {
+    // Reading the centerline file under the store lock would stall every reader for the parse.
+    if let Some(track_id) = outcome.centerline_needed {
+        let loader = meta.clone();
+        let centerline = tokio::task::spawn_blocking(move || loader.get_track_centerline(track_id))
+            .await
+            .ok()
+            .flatten();
+        let mut store = store.write().await;
+        if store.laps.distance.track_id() == Some(track_id) {
+            store.laps.distance.set_centerline(centerline);
+        }
+    }
+
+    broadcaster.send_frame(frame, frame_flags);
+
+    for event in &outcome.tyre_events {
+        announce_tyre_event(event, broadcaster);
+    }
+    for alert in &outcome.temperature_alerts {
+        announce_temperature_alert(alert, broadcaster);
+    }
+
+    if let (Some(update), Some(personal_bests)) = (outcome.personal_best_update, personal_bests) {
+        announce_personal_best(&update, broadcaster);
+        let snapshot = personal_bests.read().await.snapshot();
+        save_personal_bests(snapshot).await;
+    }
+
+    for update in &outcome.performance_updates {
+        announce_performance_test(update, broadcaster);
+    }
+    if let Some(performance) = performance.filter(|_| !outcome.performance_updates.is_empty()) {
+        let snapshot = performance.read().await.snapshot();
+        save_performance(snapshot).await;
+    }
+
+    if let Some(dyno) = dyno.filter(|_| outcome.flush_dyno) {
+        let snapshot = dyno.write().await.take_snapshot();
+        if let Some(snapshot) = snapshot {
+            save_dyno(snapshot).await;
+        }
+    }
+
+    if outcome.should_stop_record {
         if let Some(record_state) = record_state {
-            let _ = stop_recording_internal(record_state).await;
+            let _ = stop_recording_internal(record_state, broadcaster).await;
         }
     }
 
-    if should_start_record {
+    if outcome.should_start_record {
         if let Some(record_state) = record_state {
-            maybe_start_recording(record_state, now_ms).await;
+            maybe_start_recording(record_state, now_ms, broadcaster).await;
         }
     }
+
+    outcome.session_index
+}
+
+fn announce_personal_best(update: &PersonalBestUpdate, broadcaster: &Broadcaster) {
+    info!(
+        car_id = update.record.car_id,
+        track_id = update.record.track_id,
+        lap_time_ms = update.record.lap_time_ms,
+        previous_lap_time_ms = ?update.previous_lap_time_ms,
+        "personal best"
+    );
+    broadcaster.send(|sequence| PersonalBestMessage {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms: now_epoch_ms(),
+        monotonic_ms: broadcaster.monotonic_ms(),
+        sequence,
+        message_type: "personal_best",
+        record: update.record.summary(),
+        previous_lap_time_ms: update.previous_lap_time_ms,
+    });
+    broadcaster.event(Event::PersonalBest {
+        car_id: update.record.car_id,
+        track_id: update.record.track_id,
+        lap_time_ms: update.record.lap_time_ms,
+        previous_lap_time_ms: update.previous_lap_time_ms,
+    });
+}
+
+fn announce_tyre_event(event: &TyreEvent, broadcaster: &Broadcaster) {
+    broadcaster.send(|sequence| TyreEventMessage {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms: now_epoch_ms(),
+        monotonic_ms: broadcaster.monotonic_ms(),
+        sequence,
+        message_type: "tyre_event",
+        event: event.clone(),
+    });
+}
+
+fn announce_temperature_alert(alert: &TyreTemperatureAlert, broadcaster: &Broadcaster) {
+    info!(
+        wheel = ?alert.wheel,
+        temp_c = alert.temp_c,
+        threshold_c = alert.threshold_c,
+        "tyre temperature alert"
+    );
+    broadcaster.send(|sequence| TyreTemperatureAlertMessage {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms: now_epoch_ms(),
+        monotonic_ms: broadcaster.monotonic_ms(),
+        sequence,
+        message_type: "tyre_temperature_alert",
+        alert: alert.clone(),
+    });
+}
+
+fn announce_performance_test(update: &PerformanceUpdate, broadcaster: &Broadcaster) {
+    info!(
+        car_id = update.record.car_id,
+        kind = ?update.record.result.kind,
+        time_ms = update.record.result.time_ms,
+        previous_best_ms = ?update.previous_best_ms,
+        "performance test"
+    );
+    broadcaster.send(|sequence| PerformanceTestMessage {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms: now_epoch_ms(),
+        monotonic_ms: broadcaster.monotonic_ms(),
+        sequence,
+        message_type: "performance_test",
+        record: update.record.clone(),
+        is_best: update.is_best(),
+        previous_best_ms: update.previous_best_ms,
+    });
 }
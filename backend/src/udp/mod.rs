@@ -14,13 +14,24 @@ use crate::app::{
     DetectCommand, DetectEvent, DetectState, DetectStatus, DetectStore, PacketInfo, RawPacketSnapshot,
     RecordState, UdpConfig,
 };
+use crate::constants::{PACKET_STATS_INTERVAL_MS, SCHEMA_VERSION};
+use crate::dyno::DynoStore;
 use crate::meta::{self, MetadataStore, TrackDetector};
+use crate::performance::PerformanceStore;
 use crate::recording::record_raw_packet;
-use crate::telemetry::apply_frame;
-use crate::utils::monotonic_ms;
+use crate::records::PersonalBestStore;
+use crate::telemetry::{apply_frame, FrameContext};
+use crate::utils::{monotonic_ms, now_epoch_ms};
+use crate::ws::{Broadcaster, PacketStatsMessage};
 use telemetry_core::crypto;
 use telemetry_core::parser;
 
+mod order;
+mod stats;
+pub use order::{FrameFlags, FrameOrder};
+pub use stats::{PacketStats, PacketStatsSnapshot, PacketSummary};
+
+#[allow(clippy::too_many_arguments)]
 pub async fn udp_loop(
     udp_port: u16,
     mut config_rx: watch::Receiver<UdpConfig>,
@@ -32,6 +43,10 @@ pub async fn udp_loop(
     start: Instant,
     demo_active: Arc<AtomicBool>,
     record_state: Arc<Mutex<RecordState>>,
+    personal_bests: Arc<RwLock<PersonalBestStore>>,
+    dyno: Arc<RwLock<DynoStore>>,
+    performance: Arc<RwLock<PerformanceStore>>,
+    broadcaster: Broadcaster,
 ) -> std::io::Result<()> {
     let mut config = config_rx.borrow().clone();
     let mut socket = bind_udp_socket(config.bind_addr, udp_port).await?;
@@ -41,6 +56,18 @@ pub async fn udp_loop(
     let mut buf = [0u8; 4096];
     let mut last_inspect_log_ms: u64 = 0;
     let mut track_detector = TrackDetector::new();
+    let mut frame_order = FrameOrder::new();
+    let mut packet_stats = PacketStats::new();
+    let mut stats_tick = time::interval(Duration::from_millis(PACKET_STATS_INTERVAL_MS));
+    let frame_context = FrameContext {
+        store: &store,
+        meta: &meta,
+        broadcaster: &broadcaster,
+        record_state: Some(&record_state),
+        personal_bests: Some(&personal_bests),
+        dyno: Some(&dyno),
+        performance: Some(&performance),
+    };
 
     loop {
         tokio::select! {
@@ -153,6 +180,10 @@ pub async fn udp_loop(
             recv = socket.recv_from(&mut buf) => {
                 let (len, source) = recv?;
                 if demo_active.load(Ordering::Relaxed) {
+                    // Demo playback ignores the PS5, but the link still reflects it.
+                    if config.ps5_ip.is_none_or(|ps5_ip| source.ip() == ps5_ip) {
+                        store.write().await.link.record_packet(monotonic_ms(start));
+                    }
                     continue;
                 }
                 let payload = match crypto::decrypt_packet(&buf[..len]) {
@@ -259,19 +290,40 @@ pub async fn udp_loop(
                     raw_snapshot: Some(raw_snapshot),
                 };
 
-                apply_frame(
-                    &store,
-                    &meta,
+                let frame_flags = frame_order.check(frame.packet_id);
+                let session_index = apply_frame(
+                    &frame_context,
                     &mut track_detector,
                     &frame,
                     &packet_meta,
                     now_ms,
+                    frame_flags,
                     Some(packet_info),
-                    Some(&record_state),
                 )
                 .await;
+                packet_stats.set_session(session_index);
+                packet_stats.record(frame.packet_id, frame_flags, now_ms);
                 record_raw_packet(&record_state, now_ms, &buf[..len]).await;
             }
+            _ = stats_tick.tick() => {
+                let now_ms = monotonic_ms(start);
+                let snapshot = {
+                    let mut store = store.write().await;
+                    let snapshot = packet_stats.snapshot(now_ms);
+                    store.packet_stats = snapshot.clone();
+                    snapshot
+                };
+                if snapshot.rolling.received > 0 {
+                    broadcaster.send(|sequence| PacketStatsMessage {
+                        schema_version: SCHEMA_VERSION,
+                        timestamp_ms: now_epoch_ms(),
+                        monotonic_ms: now_ms,
+                        sequence,
+                        message_type: "packet_stats",
+                        stats: snapshot,
+                    });
+                }
+            }
             changed = config_rx.changed() => {
                 if changed.is_err() {
                     break;
@@ -0,0 +1,62 @@
+// Packet id ordering: flags packets GT7 repeats or delivers late, from a window of recent ids.
+
+use std::collections::VecDeque;
+
+use crate::constants::FRAME_ORDER_WINDOW;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameFlags {
+    pub duplicate: bool,
+    pub out_of_order: bool,
+}
+
+pub struct FrameOrder {
+    highest: Option<i32>,
+    recent: VecDeque<i32>,
+}
+
+impl FrameOrder {
+    pub fn new() -> Self {
+        Self {
+            highest: None,
+            recent: VecDeque::with_capacity(FRAME_ORDER_WINDOW),
+        }
+    }
+
+    pub fn highest(&self) -> Option<i32> {
+        self.highest
+    }
+
+    pub fn check(&mut self, packet_id: Option<i32>) -> FrameFlags {
+        let Some(packet_id) = packet_id else {
+            return FrameFlags::default();
+        };
+        // A jump back further than the window is a restarted counter, not a late packet.
+        let restarted = self
+            .highest
+            .is_some_and(|highest| highest.saturating_sub(packet_id) > FRAME_ORDER_WINDOW as i32);
+        if restarted {
+            self.highest = None;
+            self.recent.clear();
+        }
+        let duplicate = self.recent.contains(&packet_id);
+        let out_of_order = !duplicate && self.highest.is_some_and(|highest| packet_id < highest);
+        if !duplicate {
+            if self.recent.len() >= FRAME_ORDER_WINDOW {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(packet_id);
+        }
+        self.highest = self.highest.max(Some(packet_id));
+        FrameFlags {
+            duplicate,
+            out_of_order,
+        }
+    }
+}
+
+impl Default for FrameOrder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,182 @@
+// Packet loss, ordering and jitter statistics from GT7 packet ids and arrival times.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use super::FrameFlags;
+use crate::constants::PACKET_STATS_WINDOW_MS;
+
+const PACKETS_PER_SECOND: f32 = 60.0;
+
+#[derive(Clone, Copy, Debug)]
+struct PacketRecord {
+    arrival_ms: u64,
+    duplicate: bool,
+    reordered: bool,
+    // Ids skipped before this packet; -1 when a late packet fills an earlier gap.
+    lost: i64,
+    // Arrival interval minus the interval the packet ids imply, for consecutive in-order packets.
+    deviation_ms: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct PacketTotals {
+    received: u64,
+    duplicated: u64,
+    reordered: u64,
+    lost: i64,
+    first_ms: Option<u64>,
+    last_ms: u64,
+    deviation_sum_ms: f64,
+    deviations: u64,
+}
+
+impl PacketTotals {
+    fn add(&mut self, record: &PacketRecord) {
+        self.received += 1;
+        self.duplicated += u64::from(record.duplicate);
+        self.reordered += u64::from(record.reordered);
+        self.lost += record.lost;
+        self.first_ms.get_or_insert(record.arrival_ms);
+        self.last_ms = record.arrival_ms;
+        if let Some(deviation_ms) = record.deviation_ms {
+            self.deviation_sum_ms += f64::from(deviation_ms.abs());
+            self.deviations += 1;
+        }
+    }
+
+    fn summary(&self) -> PacketSummary {
+        let lost = self.lost.max(0) as u64;
+        let expected = self.received - self.duplicated + lost;
+        let duration_ms = self
+            .first_ms
+            .map_or(0, |first_ms| self.last_ms.saturating_sub(first_ms));
+        PacketSummary {
+            received: self.received,
+            lost,
+            duplicated: self.duplicated,
+            reordered: self.reordered,
+            loss_ratio: (expected > 0).then(|| lost as f32 / expected as f32),
+            jitter_ms: (self.deviations > 0)
+                .then(|| (self.deviation_sum_ms / self.deviations as f64) as f32),
+            // Distinct packets over the time between the first and last arrival.
+            effective_hz: (duration_ms > 0).then(|| {
+                (self.received - self.duplicated).saturating_sub(1) as f32 * 1_000.0
+                    / duration_ms as f32
+            }),
+            duration_ms,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PacketSummary {
+    pub received: u64,
+    pub lost: u64,
+    pub duplicated: u64,
+    pub reordered: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loss_ratio: Option<f32>,
+    // Mean absolute deviation of arrival intervals from the 60 Hz packet clock.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jitter_ms: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_hz: Option<f32>,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PacketStatsSnapshot {
+    pub session_index: u64,
+    pub session: PacketSummary,
+    pub rolling: PacketSummary,
+    pub window_ms: u64,
+}
+
+pub struct PacketStats {
+    // Id and arrival time of the newest in-order packet, which is also the highest id seen.
+    last_packet: Option<(i32, u64)>,
+    session_index: u64,
+    session: PacketTotals,
+    recent: VecDeque<PacketRecord>,
+}
+
+impl PacketStats {
+    pub fn new() -> Self {
+        Self {
+            last_packet: None,
+            session_index: 0,
+            session: PacketTotals::default(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    // Takes the flags the ingest loop already computed for the frame channel.
+    pub fn record(&mut self, packet_id: Option<i32>, flags: FrameFlags, now_ms: u64) {
+        let Some(packet_id) = packet_id else {
+            return;
+        };
+        let mut record = PacketRecord {
+            arrival_ms: now_ms,
+            duplicate: flags.duplicate,
+            reordered: flags.out_of_order,
+            lost: 0,
+            deviation_ms: None,
+        };
+        if flags.out_of_order {
+            record.lost = -1;
+        } else if !flags.duplicate {
+            // A restarted counter lands below the old highest id and counts no gap.
+            if let Some((last_id, last_ms)) =
+                self.last_packet.filter(|(last_id, _)| packet_id > *last_id)
+            {
+                record.lost = i64::from(packet_id - last_id - 1);
+                let expected_ms = (packet_id - last_id) as f32 * 1_000.0 / PACKETS_PER_SECOND;
+                record.deviation_ms = Some(now_ms.saturating_sub(last_ms) as f32 - expected_ms);
+            }
+            self.last_packet = Some((packet_id, now_ms));
+        }
+        self.session.add(&record);
+        self.recent.push_back(record);
+        self.evict(now_ms);
+    }
+
+    // Session counters restart when the tracker reports a new session index.
+    pub fn set_session(&mut self, session_index: u64) {
+        if session_index != self.session_index {
+            self.session_index = session_index;
+            self.session = PacketTotals::default();
+        }
+    }
+
+    pub fn snapshot(&mut self, now_ms: u64) -> PacketStatsSnapshot {
+        self.evict(now_ms);
+        let mut rolling = PacketTotals::default();
+        for record in &self.recent {
+            rolling.add(record);
+        }
+        PacketStatsSnapshot {
+            session_index: self.session_index,
+            session: self.session.summary(),
+            rolling: rolling.summary(),
+            window_ms: PACKET_STATS_WINDOW_MS,
+        }
+    }
+
+    fn evict(&mut self, now_ms: u64) {
+        while self
+            .recent
+            .front()
+            .is_some_and(|record| now_ms.saturating_sub(record.arrival_ms) > PACKET_STATS_WINDOW_MS)
+        {
+            self.recent.pop_front();
+        }
+    }
+}
+
+impl Default for PacketStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,12 @@
-// Shared utility helpers for timestamps and sequencing.
+// Shared utility helpers for timestamps, sequencing and file writes.
 
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use serde::Serialize;
+use tokio::sync::Mutex;
 use tokio::time::Instant;
 
 pub fn now_epoch_ms() -> u64 {
@@ -27,3 +31,93 @@ pub fn hex_encode(bytes: &[u8]) -> String {
 pub fn next_sequence(sequence: &AtomicU64) -> u64 {
     sequence.fetch_add(1, Ordering::Relaxed) + 1
 }
+
+// Writes through a temporary file and renames it, so a crash never leaves a truncated file.
+pub async fn write_json_atomic<T: Serialize + ?Sized>(
+    path: &Path,
+    value: &T,
+) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let payload = serde_json::to_vec(value).map_err(std::io::Error::other)?;
+    let tmp_path = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp_path, payload).await?;
+    tokio::fs::rename(&tmp_path, path).await
+}
+
+// A JSON file saved from snapshots taken under its store's lock. Snapshots are numbered when
+// taken and written one at a time, and one older than the last write is dropped, so a slow
+// save never lands over a newer one.
+#[derive(Clone)]
+pub struct JsonFile {
+    path: PathBuf,
+    taken: Arc<AtomicU64>,
+    written: Arc<Mutex<u64>>,
+}
+
+impl JsonFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            taken: Arc::new(AtomicU64::new(0)),
+            written: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn snapshot<T>(&self, value: T) -> JsonSnapshot<T> {
+        JsonSnapshot {
+            file: self.clone(),
+            generation: next_sequence(&self.taken),
+            value,
+        }
+    }
+}
+
+pub struct JsonSnapshot<T> {
+    file: JsonFile,
+    generation: u64,
+    value: T,
+}
+
+impl<T: Serialize> JsonSnapshot<T> {
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    pub async fn save(&self) -> std::io::Result<()> {
+        let mut written = self.file.written.lock().await;
+        if *written > self.generation {
+            return Ok(());
+        }
+        write_json_atomic(self.file.path(), &self.value).await?;
+        *written = self.generation;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stale_snapshot_does_not_overwrite_newer_save() {
+        let dir = std::env::temp_dir().join(format!("apextelemetry-json-{}", std::process::id()));
+        let file = JsonFile::new(dir.join("values.json"));
+        let older = file.snapshot(vec![1]);
+        let newer = file.snapshot(vec![1, 2]);
+
+        newer.save().await.expect("newer snapshot saves");
+        older.save().await.expect("stale snapshot is skipped");
+
+        let saved: Vec<i32> =
+            serde_json::from_slice(&std::fs::read(file.path()).expect("file written"))
+                .expect("valid json");
+        assert_eq!(saved, vec![1, 2]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
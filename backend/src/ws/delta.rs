@@ -0,0 +1,236 @@
+// Keyframe-plus-delta encoding of state snapshots for state_delta messages.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde_json::{Map, Value};
+
+use super::Subscription;
+use crate::constants::STATE_KEYFRAME_INTERVAL_MS;
+
+pub struct StateDelta {
+    // None for a keyframe, which carries every field.
+    pub base_sequence: Option<u64>,
+    pub state: Map<String, Value>,
+    pub removed: Vec<String>,
+}
+
+pub struct DeltaEncoder {
+    last: Option<(u64, Map<String, Value>)>,
+    last_keyframe_ms: Option<u64>,
+}
+
+impl DeltaEncoder {
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            last_keyframe_ms: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    // The last state sent and its sequence, for clients that need to resynchronise.
+    pub fn keyframe(&self) -> Option<(u64, &Map<String, Value>)> {
+        self.last
+            .as_ref()
+            .map(|(sequence, state)| (*sequence, state))
+    }
+
+    // Returns None when nothing changed since the last message.
+    pub fn next(
+        &mut self,
+        sequence: u64,
+        state: Map<String, Value>,
+        now_ms: u64,
+    ) -> Option<StateDelta> {
+        let keyframe_due = self
+            .last_keyframe_ms
+            .is_none_or(|at| now_ms.saturating_sub(at) >= STATE_KEYFRAME_INTERVAL_MS);
+        let delta = match self.last.as_ref() {
+            Some((base_sequence, previous)) if !keyframe_due => {
+                let changed: Map<String, Value> = state
+                    .iter()
+                    .filter(|(key, value)| previous.get(*key) != Some(*value))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+                let removed: Vec<String> = previous
+                    .keys()
+                    .filter(|key| !state.contains_key(*key))
+                    .cloned()
+                    .collect();
+                if changed.is_empty() && removed.is_empty() {
+                    return None;
+                }
+                StateDelta {
+                    base_sequence: Some(*base_sequence),
+                    state: changed,
+                    removed,
+                }
+            }
+            _ => {
+                self.last_keyframe_ms = Some(now_ms);
+                StateDelta {
+                    base_sequence: None,
+                    state: state.clone(),
+                    removed: Vec::new(),
+                }
+            }
+        };
+        self.last = Some((sequence, state));
+        Some(delta)
+    }
+}
+
+impl Default for DeltaEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+type Baseline = (u64, Map<String, Value>);
+
+// The broadcast state_delta stream: how many connections take it, and the baseline the state
+// task last sent so a joining client can start from a keyframe.
+#[derive(Clone)]
+pub struct SharedDelta {
+    clients: Arc<AtomicUsize>,
+    baseline: Arc<Mutex<Option<Baseline>>>,
+}
+
+impl SharedDelta {
+    pub fn new() -> Self {
+        Self {
+            clients: Arc::new(AtomicUsize::new(0)),
+            baseline: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn has_clients(&self) -> bool {
+        self.clients.load(Ordering::Relaxed) > 0
+    }
+
+    pub fn publish(&self, encoder: &DeltaEncoder) {
+        let baseline = encoder
+            .keyframe()
+            .map(|(sequence, state)| (sequence, state.clone()));
+        *self.baseline.lock().unwrap_or_else(PoisonError::into_inner) = baseline;
+    }
+
+    pub fn baseline(&self) -> Option<Baseline> {
+        self.baseline
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn listener(&self) -> DeltaListener {
+        DeltaListener {
+            clients: self.clients.clone(),
+            counted: false,
+        }
+    }
+}
+
+impl Default for SharedDelta {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Counts one connection among the shared stream's clients while its subscription accepts it.
+pub struct DeltaListener {
+    clients: Arc<AtomicUsize>,
+    counted: bool,
+}
+
+impl DeltaListener {
+    pub fn update(&mut self, subscription: &Subscription) {
+        let wanted = subscription.accepts_broadcast("state_delta");
+        if wanted == self.counted {
+            return;
+        }
+        if wanted {
+            self.clients.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.clients.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.counted = wanted;
+    }
+}
+
+impl Drop for DeltaListener {
+    fn drop(&mut self) {
+        if self.counted {
+            self.clients.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn state(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn unchanged_state_sends_nothing() {
+        let mut encoder = DeltaEncoder::new();
+        let first = encoder
+            .next(1, state(json!({ "speed_kph": 120.0 })), 0)
+            .expect("first message is a keyframe");
+        assert_eq!(first.base_sequence, None);
+        assert!(encoder
+            .next(2, state(json!({ "speed_kph": 120.0 })), 50)
+            .is_none());
+        // The skipped message leaves the baseline on the keyframe.
+        assert_eq!(encoder.keyframe().map(|(sequence, _)| sequence), Some(1));
+    }
+
+    #[test]
+    fn delta_carries_changed_and_removed_fields() {
+        let mut encoder = DeltaEncoder::new();
+        encoder.next(1, state(json!({ "speed_kph": 120.0, "gear": 3 })), 0);
+        let delta = encoder
+            .next(2, state(json!({ "speed_kph": 130.0, "rpm": 6000.0 })), 50)
+            .expect("fields changed");
+        assert_eq!(delta.base_sequence, Some(1));
+        assert_eq!(
+            delta.state,
+            state(json!({ "speed_kph": 130.0, "rpm": 6000.0 }))
+        );
+        assert_eq!(delta.removed, vec!["gear".to_string()]);
+    }
+
+    #[test]
+    fn keyframe_is_resent_after_the_interval() {
+        let mut encoder = DeltaEncoder::new();
+        encoder.next(1, state(json!({ "speed_kph": 120.0 })), 0);
+        let delta = encoder
+            .next(
+                2,
+                state(json!({ "speed_kph": 121.0 })),
+                STATE_KEYFRAME_INTERVAL_MS - 1,
+            )
+            .expect("field changed");
+        assert_eq!(delta.base_sequence, Some(1));
+        let keyframe = encoder
+            .next(
+                3,
+                state(json!({ "speed_kph": 121.0 })),
+                STATE_KEYFRAME_INTERVAL_MS,
+            )
+            .expect("keyframe is due even without changes");
+        assert_eq!(keyframe.base_sequence, None);
+        assert_eq!(keyframe.state, state(json!({ "speed_kph": 121.0 })));
+    }
+}
@@ -0,0 +1,96 @@
+// Wire encodings for stream messages, negotiated per connection with JSON as the fallback.
+
+use std::sync::OnceLock;
+
+use axum::extract::ws::Message;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    #[default]
+    Json,
+    #[serde(rename = "msgpack")]
+    MsgPack,
+    Cbor,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [Encoding::Json, Encoding::MsgPack, Encoding::Cbor];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Json => "json",
+            Encoding::MsgPack => "msgpack",
+            Encoding::Cbor => "cbor",
+        }
+    }
+
+    // Unknown names fall back to JSON; the hello reports what was actually chosen.
+    pub fn negotiate(requested: Option<&str>) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|encoding| requested == Some(encoding.as_str()))
+            .unwrap_or_default()
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            Encoding::Json => serde_json::from_slice(bytes).map_err(|err| err.to_string()),
+            Encoding::MsgPack => rmp_serde::from_slice(bytes).map_err(|err| err.to_string()),
+            Encoding::Cbor => ciborium::from_reader(bytes).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+// Object-safe serialization so a message can be kept once and encoded on demand.
+pub trait Encode: Send + Sync {
+    fn encode(&self, encoding: Encoding) -> Option<Vec<u8>>;
+}
+
+impl<T: Serialize + Send + Sync> Encode for T {
+    fn encode(&self, encoding: Encoding) -> Option<Vec<u8>> {
+        match encoding {
+            Encoding::Json => serde_json::to_vec(self).ok(),
+            // Named fields keep the same shape as the JSON messages.
+            Encoding::MsgPack => rmp_serde::to_vec_named(self).ok(),
+            Encoding::Cbor => {
+                let mut out = Vec::new();
+                ciborium::into_writer(self, &mut out).ok()?;
+                Some(out)
+            }
+        }
+    }
+}
+
+// Per-encoding cache: each message is serialized at most once per encoding, however many
+// connections receive it.
+#[derive(Default)]
+pub struct EncodedCache {
+    slots: [OnceLock<Option<Vec<u8>>>; 3],
+}
+
+impl EncodedCache {
+    pub fn get(&self, encoding: Encoding, message: &dyn Encode) -> Option<&[u8]> {
+        let slot = &self.slots[encoding as usize];
+        slot.get_or_init(|| message.encode(encoding)).as_deref()
+    }
+}
+
+// Goes through JSON text so f32 fields keep their short form instead of widening to f64 digits.
+pub fn json_map<T: Serialize>(value: &T) -> Map<String, Value> {
+    serde_json::to_string(value)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub fn frame(encoding: Encoding, bytes: &[u8]) -> Message {
+    match encoding {
+        // serde_json only emits valid UTF-8.
+        Encoding::Json => Message::Text(String::from_utf8_lossy(bytes).into_owned()),
+        Encoding::MsgPack | Encoding::Cbor => Message::Binary(bytes.to_vec()),
+    }
+}
@@ -0,0 +1,12 @@
+// Raw frame channel: every parsed packet in arrival order, flagged when GT7 repeats or reorders one.
+
+use std::sync::Arc;
+
+use super::{FrameMessage, Outbound};
+
+// Sent on its own channel so 60 Hz frames neither crowd the main broadcast queue nor the
+// replay buffer. `outbound` is the full frame; clients with a field selection re-encode `message`.
+pub struct FrameBroadcast {
+    pub outbound: Arc<Outbound>,
+    pub message: FrameMessage,
+}
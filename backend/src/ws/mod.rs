@@ -1,18 +1,237 @@
 // WebSocket transport layer for telemetry streaming.
 
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
 use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
-use axum::extract::State as AxumState;
+use axum::extract::{Query, State as AxumState};
 use axum::response::IntoResponse;
 use futures::StreamExt;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use tokio::sync::broadcast;
+use tokio::time::{self, Instant, Interval, MissedTickBehavior};
 use tracing::{info, warn};
 
-use crate::app::AppState;
-use crate::constants::SCHEMA_VERSION;
-use crate::model::{Sample, State as TelemetryState};
+use crate::analysis::{TyreEvent, TyreTemperatureAlert};
+use crate::app::{AppState, SessionState, TelemetryStore};
+use crate::constants::{EVENT_HISTORY_CAP, SCHEMA_VERSION, WINDOW_DURATION_MS, WINDOW_STRIDE_MS};
+use crate::events::Event;
+use crate::link::LinkStatus;
+use crate::model::{ChannelSamples, State as TelemetryState, TelemetryFrame};
+use crate::performance::PerformanceRecord;
+use crate::records::PersonalBestSummary;
+use crate::udp::{FrameFlags, PacketStatsSnapshot};
 use crate::utils::{monotonic_ms, next_sequence, now_epoch_ms};
 
+mod delta;
+mod encoding;
+mod frames;
+mod replay;
+mod sse;
+mod subscription;
+pub use delta::{DeltaEncoder, DeltaListener, SharedDelta, StateDelta};
+use encoding::EncodedCache;
+pub use encoding::{json_map, Encode, Encoding};
+pub use frames::FrameBroadcast;
+pub use replay::ReplayBuffer;
+pub use sse::sse_handler;
+pub use subscription::{
+    ClientMessage, ClientRequest, History, HistoryKind, Subscription, SUBSCRIBABLE_TYPES,
+};
+
+// Every outbound message carries its type and sequence so each connection can filter it
+// without parsing the JSON again.
+pub trait Envelope: Serialize {
+    fn message_type(&self) -> &'static str;
+    fn sequence(&self) -> u64;
+}
+
+macro_rules! impl_envelope {
+    ($($message:ty),+ $(,)?) => {
+        $(impl Envelope for $message {
+            fn message_type(&self) -> &'static str {
+                self.message_type
+            }
+
+            fn sequence(&self) -> u64 {
+                self.sequence
+            }
+        })+
+    };
+}
+
+impl<S: Serialize> Envelope for StateUpdateMessage<S> {
+    fn message_type(&self) -> &'static str {
+        self.message_type
+    }
+
+    fn sequence(&self) -> u64 {
+        self.sequence
+    }
+}
+
+impl<F: Serialize> Envelope for FrameMessage<F> {
+    fn message_type(&self) -> &'static str {
+        self.message_type
+    }
+
+    fn sequence(&self) -> u64 {
+        self.sequence
+    }
+}
+
+impl_envelope!(
+    HandshakeHello,
+    StateDeltaMessage,
+    SamplesWindowMessage,
+    PersonalBestMessage,
+    TyreEventMessage,
+    TyreTemperatureAlertMessage,
+    PerformanceTestMessage,
+    LinkStatusMessage,
+    PacketStatsMessage,
+    SubscriptionMessage,
+    HistoryMessage,
+    ErrorMessage,
+    EventMessage,
+    LagMessage,
+    ResyncRequiredMessage,
+);
+
+pub struct Outbound {
+    pub message_type: &'static str,
+    pub sequence: u64,
+    message: Box<dyn Encode>,
+    encoded: EncodedCache,
+}
+
+impl Outbound {
+    pub fn new<T: Envelope + Send + Sync + 'static>(message: T) -> Arc<Self> {
+        Arc::new(Self {
+            message_type: message.message_type(),
+            sequence: message.sequence(),
+            message: Box::new(message),
+            encoded: EncodedCache::default(),
+        })
+    }
+
+    pub fn encoded(&self, encoding: Encoding) -> Option<&[u8]> {
+        self.encoded.get(encoding, self.message.as_ref())
+    }
+
+    pub fn frame(&self, encoding: Encoding) -> Option<Message> {
+        self.encoded(encoding)
+            .map(|bytes| encoding::frame(encoding, bytes))
+    }
+}
+
+#[derive(Clone)]
+pub struct Broadcaster {
+    pub tx: broadcast::Sender<Arc<Outbound>>,
+    pub sequence: Arc<AtomicU64>,
+    // Frames bypass the replay buffer, so they are numbered apart from the broadcast stream.
+    pub frame_sequence: Arc<AtomicU64>,
+    pub start_instant: Instant,
+    pub replay: Arc<Mutex<ReplayBuffer>>,
+    pub frame_tx: broadcast::Sender<Arc<FrameBroadcast>>,
+    pub events: Arc<Mutex<VecDeque<EventMessage>>>,
+    pub state_delta: SharedDelta,
+}
+
+impl Broadcaster {
+    pub fn monotonic_ms(&self) -> u64 {
+        monotonic_ms(self.start_instant)
+    }
+
+    // Builds the message with the next broadcast sequence and sends it. Returns the sequence.
+    pub fn send<T: Envelope + Send + Sync + 'static>(&self, build: impl FnOnce(u64) -> T) -> u64 {
+        self.send_with(|sequence| Some(build(sequence)))
+            .expect("message was built")
+    }
+
+    // Numbering, buffering and sending under one lock keeps sequences, the replay buffer and the
+    // channel in the same order. A sequence is only taken when `build` returns a message.
+    pub fn send_with<T: Envelope + Send + Sync + 'static>(
+        &self,
+        build: impl FnOnce(u64) -> Option<T>,
+    ) -> Option<u64> {
+        let mut replay = self.replay.lock().unwrap_or_else(PoisonError::into_inner);
+        let sequence = self.sequence.load(Ordering::Relaxed) + 1;
+        let outbound = Outbound::new(build(sequence)?);
+        self.sequence.store(sequence, Ordering::Relaxed);
+        replay.push(outbound.clone(), self.monotonic_ms());
+        let _ = self.tx.send(outbound);
+        Some(sequence)
+    }
+
+    // Subscribes under the same lock, so the messages after `sequence` and the new receiver
+    // neither overlap nor leave a gap. None when some of them were already dropped.
+    pub fn resume(
+        &self,
+        sequence: u64,
+    ) -> (
+        broadcast::Receiver<Arc<Outbound>>,
+        Option<Vec<Arc<Outbound>>>,
+    ) {
+        let replay = self.replay.lock().unwrap_or_else(PoisonError::into_inner);
+        (self.tx.subscribe(), replay.since(sequence))
+    }
+
+    pub fn event(&self, event: Event) {
+        self.send(|sequence| {
+            let message = EventMessage {
+                schema_version: SCHEMA_VERSION,
+                timestamp_ms: now_epoch_ms(),
+                monotonic_ms: self.monotonic_ms(),
+                sequence,
+                message_type: "event",
+                event,
+            };
+            let mut events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
+            if events.len() >= EVENT_HISTORY_CAP {
+                events.pop_front();
+            }
+            events.push_back(message.clone());
+            message
+        });
+    }
+
+    pub fn event_history(&self) -> Vec<EventMessage> {
+        let events = self.events.lock().unwrap_or_else(PoisonError::into_inner);
+        events.iter().cloned().collect()
+    }
+
+    // Frames are only built while some client is subscribed to them.
+    pub fn send_frame(&self, frame: &TelemetryFrame, flags: FrameFlags) {
+        if self.frame_tx.receiver_count() == 0 {
+            return;
+        }
+        let message = FrameMessage {
+            schema_version: SCHEMA_VERSION,
+            timestamp_ms: now_epoch_ms(),
+            monotonic_ms: self.monotonic_ms(),
+            sequence: next_sequence(self.frame_sequence.as_ref()),
+            message_type: "frame",
+            packet_id: frame.packet_id,
+            duplicate: flags.duplicate,
+            out_of_order: flags.out_of_order,
+            frame: frame.clone(),
+        };
+        let outbound = Outbound::new(message.clone());
+        let _ = self
+            .frame_tx
+            .send(Arc::new(FrameBroadcast { outbound, message }));
+    }
+
+    pub fn oldest_sequence(&self) -> Option<u64> {
+        let replay = self.replay.lock().unwrap_or_else(PoisonError::into_inner);
+        replay.oldest_sequence()
+    }
+}
+
 #[derive(Serialize)]
 pub struct HandshakeHello {
     pub schema_version: &'static str,
@@ -23,17 +242,89 @@ pub struct HandshakeHello {
     pub message_type: &'static str,
     pub server_version: &'static str,
     pub capabilities: Vec<&'static str>,
+    pub encoding: &'static str,
+    pub encodings: Vec<&'static str>,
+}
+
+// Clients with a field selection get the state as a trimmed JSON object.
+#[derive(Serialize)]
+pub struct StateUpdateMessage<S = TelemetryState> {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    pub state: S,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_timestamp_ms: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct EventMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+// One parsed packet, unthrottled. Clients with a field selection get the frame as a trimmed
+// JSON object.
+#[derive(Clone, Serialize)]
+pub struct FrameMessage<F = TelemetryFrame> {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub packet_id: Option<i32>,
+    // Same packet_id as a frame already sent.
+    pub duplicate: bool,
+    // Arrived after a frame with a higher packet_id.
+    pub out_of_order: bool,
+    pub frame: F,
+}
+
+impl FrameMessage {
+    pub fn with_frame<F>(&self, frame: F) -> FrameMessage<F> {
+        FrameMessage {
+            schema_version: self.schema_version,
+            timestamp_ms: self.timestamp_ms,
+            monotonic_ms: self.monotonic_ms,
+            sequence: self.sequence,
+            message_type: self.message_type,
+            packet_id: self.packet_id,
+            duplicate: self.duplicate,
+            out_of_order: self.out_of_order,
+            frame,
+        }
+    }
 }
 
+// Without base_sequence the message is a keyframe carrying every field; otherwise only the
+// fields that changed since that sequence, plus the ones that were dropped.
 #[derive(Serialize)]
-pub struct StateUpdateMessage {
+pub struct StateDeltaMessage {
     pub schema_version: &'static str,
     pub timestamp_ms: u64,
     pub monotonic_ms: u64,
     pub sequence: u64,
     #[serde(rename = "type")]
     pub message_type: &'static str,
-    pub state: TelemetryState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub keyframe: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_sequence: Option<u64>,
+    pub state: Map<String, Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub source_timestamp_ms: Option<u64>,
 }
@@ -43,7 +334,46 @@ pub struct SamplesWindow {
     pub start_ms: u64,
     pub end_ms: u64,
     pub stride_ms: u64,
-    pub samples: Vec<Sample>,
+    // Serialized as `channels` (name and unit) and `samples`.
+    #[serde(flatten)]
+    pub samples: ChannelSamples,
+}
+
+impl SamplesWindow {
+    // The last WINDOW_DURATION_MS of the race, one sample per WINDOW_STRIDE_MS, on the given
+    // channel selection. None outside a race or when there is nothing to show.
+    pub fn build(store: &TelemetryStore, selection: &[usize], now_ms: u64) -> Option<Self> {
+        if store.session.session_state != SessionState::InRace || store.samples.is_empty() {
+            return None;
+        }
+        let start_ms = now_ms.saturating_sub(WINDOW_DURATION_MS);
+        let mut window_samples = Vec::new();
+        let mut last_t = None;
+        for sample in store.samples.to_vec_ordered() {
+            if sample.t_ms < start_ms || sample.t_ms > now_ms {
+                continue;
+            }
+            let emit = match last_t {
+                Some(prev) => sample.t_ms.saturating_sub(prev) >= WINDOW_STRIDE_MS,
+                None => true,
+            };
+            if emit {
+                last_t = Some(sample.t_ms);
+                window_samples.push(sample);
+            }
+        }
+
+        if window_samples.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            start_ms,
+            end_ms: now_ms,
+            stride_ms: WINDOW_STRIDE_MS,
+            samples: store.sample_channels.project(selection, &window_samples),
+        })
+    }
 }
 
 #[derive(Serialize)]
@@ -58,56 +388,345 @@ pub struct SamplesWindowMessage {
     pub decimated: bool,
 }
 
+#[derive(Serialize)]
+pub struct PersonalBestMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    pub record: PersonalBestSummary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_lap_time_ms: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct TyreEventMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(flatten)]
+    pub event: TyreEvent,
+}
+
+#[derive(Serialize)]
+pub struct TyreTemperatureAlertMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(flatten)]
+    pub alert: TyreTemperatureAlert,
+}
+
+#[derive(Serialize)]
+pub struct PerformanceTestMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(flatten)]
+    pub record: PerformanceRecord,
+    pub is_best: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_best_ms: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct LinkStatusMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(flatten)]
+    pub link: LinkStatus,
+}
+
+#[derive(Serialize)]
+pub struct PacketStatsMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(flatten)]
+    pub stats: PacketStatsSnapshot,
+}
+
+#[derive(Serialize)]
+pub struct SubscriptionMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub subscription: Subscription,
+}
+
+#[derive(Serialize)]
+pub struct HistoryMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub history: History,
+}
+
+#[derive(Serialize)]
+pub struct ErrorMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    pub error: String,
+}
+
+// The connection fell behind and `skipped` messages were dropped from its queue. Broadcast
+// messages are replayed right after this notice unless a resync_required follows instead.
+#[derive(Serialize)]
+pub struct LagMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    // "broadcast" for the main stream, "frame" for the raw frame channel, which is not replayed.
+    pub channel: &'static str,
+    pub skipped: u64,
+}
+
+// Messages after `resume_from` can no longer be replayed; the client should rebuild its state
+// from the live stream and history requests.
+#[derive(Serialize)]
+pub struct ResyncRequiredMessage {
+    pub schema_version: &'static str,
+    pub timestamp_ms: u64,
+    pub monotonic_ms: u64,
+    pub sequence: u64,
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    pub reason: &'static str,
+    pub resume_from: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_sequence: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct WsQuery {
+    pub encoding: Option<String>,
+    // Last sequence the client received before reconnecting.
+    pub resume_from: Option<u64>,
+}
+
 pub async fn ws_handler(
     AxumState(app_state): AxumState<AppState>,
+    Query(query): Query<WsQuery>,
     ws: WebSocketUpgrade,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(socket, app_state))
+    let encoding = Encoding::negotiate(query.encoding.as_deref());
+    let resume_from = query.resume_from;
+    ws.on_upgrade(move |socket| handle_socket(socket, app_state, encoding, resume_from))
 }
 
-async fn handle_socket(mut socket: WebSocket, app_state: AppState) {
-    info!("ws connected");
-    let mut rx = app_state.tx.subscribe();
+async fn handle_socket(
+    mut socket: WebSocket,
+    app_state: AppState,
+    encoding: Encoding,
+    resume_from: Option<u64>,
+) {
+    info!(encoding = encoding.as_str(), resume_from, "ws connected");
+    let broadcaster = app_state.broadcaster();
+    // Highest broadcast sequence this connection has seen, delivered or filtered out.
+    let mut last_sequence =
+        resume_from.unwrap_or_else(|| app_state.sequence.load(Ordering::Relaxed));
+    let (mut rx, missed) = match resume_from {
+        Some(sequence) => {
+            let (rx, missed) = broadcaster.resume(sequence);
+            (rx, Some(missed))
+        }
+        None => (app_state.tx.subscribe(), None),
+    };
+    let mut capabilities = SUBSCRIBABLE_TYPES.to_vec();
+    capabilities.extend([
+        "subscribe",
+        "state_rate",
+        "state_fields",
+        "state_delta",
+        "resync",
+        "history",
+        "resume",
+        "frame_fields",
+        "sample_channels",
+    ]);
+    let (timestamp_ms, monotonic_ms, sequence) = reply_envelope(&app_state);
     let hello = HandshakeHello {
         schema_version: SCHEMA_VERSION,
-        timestamp_ms: now_epoch_ms(),
-        monotonic_ms: monotonic_ms(app_state.start_instant),
-        sequence: next_sequence(app_state.sequence.as_ref()),
+        timestamp_ms,
+        monotonic_ms,
+        sequence,
         message_type: "handshake_hello",
         server_version: env!("CARGO_PKG_VERSION"),
-        capabilities: vec!["state_update", "samples_window"],
+        capabilities,
+        encoding: encoding.as_str(),
+        encodings: Encoding::ALL.iter().map(Encoding::as_str).collect(),
     };
 
-    if let Ok(payload) = serde_json::to_string(&hello) {
-        if socket.send(Message::Text(payload)).await.is_err() {
+    // The hello is always JSON so a client can tell whether its encoding was accepted.
+    if let Some(frame) = Outbound::new(hello).frame(Encoding::Json) {
+        if socket.send(frame).await.is_err() {
             return;
         }
     }
 
+    let mut subscription = Subscription::new();
+    // Only runs while the client has its own state_update rate or fields.
+    let mut state_timer: Option<Interval> = None;
+    // Baseline for this client's own state_delta stream on a custom rate or field selection.
+    let mut state_delta = DeltaEncoder::new();
+    let mut delta_listener = app_state.state_delta.listener();
+    // Sequence of the last shared keyframe sent; broadcast deltas up to it are already applied.
+    let mut keyframe_sequence = 0;
+    // Only subscribed while the client wants frame messages.
+    let mut frame_rx: Option<broadcast::Receiver<Arc<FrameBroadcast>>> = None;
+
+    if let Some(missed) = missed {
+        for frame in catch_up(&app_state, &subscription, &mut last_sequence, missed)
+            .iter()
+            .filter_map(|outbound| outbound.frame(encoding))
+        {
+            if socket.send(frame).await.is_err() {
+                return;
+            }
+        }
+    }
+
     loop {
-        tokio::select! {
+        let outbound = tokio::select! {
             outbound = rx.recv() => {
                 match outbound {
-                    Ok(payload) => {
-                        if socket.send(Message::Text(payload)).await.is_err() {
-                            break;
+                    Ok(outbound) => {
+                        last_sequence = last_sequence.max(outbound.sequence);
+                        if outbound.message_type == "samples_window" && subscription.custom_samples() {
+                            // The shared window paces this client's own channel selection.
+                            custom_samples_window(&app_state, &subscription).await.into_iter().collect()
+                        } else if outbound.message_type == "state_delta" && outbound.sequence <= keyframe_sequence {
+                            continue;
+                        } else if subscription.accepts_broadcast(outbound.message_type) {
+                            vec![outbound]
+                        } else {
+                            continue;
                         }
                     }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {
-                        continue;
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(skipped, last_sequence, "ws client lagged");
+                        // A fresh receiver picks up exactly where the replay leaves off.
+                        let (next_rx, missed) = broadcaster.resume(last_sequence);
+                        rx = next_rx;
+                        let (timestamp_ms, monotonic_ms, sequence) = reply_envelope(&app_state);
+                        let mut outbound = vec![Outbound::new(LagMessage {
+                            schema_version: SCHEMA_VERSION,
+                            timestamp_ms,
+                            monotonic_ms,
+                            sequence,
+                            message_type: "lag",
+                            channel: "broadcast",
+                            skipped,
+                        })];
+                        outbound.extend(catch_up(&app_state, &subscription, &mut last_sequence, missed));
+                        outbound
                     }
                     Err(_) => break,
                 }
             }
+            frame = recv_frame(&mut frame_rx) => {
+                match frame {
+                    Ok(frame) => vec![subscription.select_frame(&frame)],
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        let (timestamp_ms, monotonic_ms, sequence) = reply_envelope(&app_state);
+                        vec![Outbound::new(LagMessage {
+                            schema_version: SCHEMA_VERSION,
+                            timestamp_ms,
+                            monotonic_ms,
+                            sequence,
+                            message_type: "lag",
+                            channel: "frame",
+                            skipped,
+                        })]
+                    }
+                    Err(_) => {
+                        frame_rx = None;
+                        Vec::new()
+                    }
+                }
+            }
+            _ = tick(&mut state_timer) => {
+                custom_state_update(&app_state, &subscription, &mut state_delta, None)
+                    .await
+                    .into_iter()
+                    .collect()
+            }
             inbound = socket.next() => {
                 match inbound {
+                    Some(Ok(message @ (Message::Text(_) | Message::Binary(_)))) => {
+                        // Requests may use JSON text or the connection's binary encoding.
+                        let request = match message {
+                            Message::Text(text) => Encoding::Json.decode(text.as_bytes()),
+                            message => encoding.decode(&message.into_data()),
+                        };
+                        let replies =
+                            handle_request(&app_state, &mut subscription, &mut state_delta, request).await;
+                        delta_listener.update(&subscription);
+                        if !subscription.custom_state() {
+                            keyframe_sequence = keyframe_through(&replies).max(keyframe_sequence);
+                        }
+                        let period = Duration::from_millis(subscription.state_interval_ms);
+                        if !subscription.custom_state() || !subscription.wants("state_update") {
+                            state_timer = None;
+                        } else if state_timer.as_ref().is_none_or(|timer| timer.period() != period) {
+                            let mut timer = time::interval(period);
+                            timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                            state_timer = Some(timer);
+                        }
+                        if !subscription.wants("frame") {
+                            frame_rx = None;
+                        } else if frame_rx.is_none() {
+                            frame_rx = Some(app_state.frame_tx.subscribe());
+                        }
+                        replies
+                    }
                     Some(Ok(Message::Ping(payload))) => {
                         if socket.send(Message::Pong(payload)).await.is_err() {
                             break;
                         }
+                        Vec::new()
                     }
                     Some(Ok(Message::Close(_))) => break,
-                    Some(Ok(_)) => {}
+                    Some(Ok(_)) => Vec::new(),
                     Some(Err(err)) => {
                         warn!(?err, "ws error");
                         break;
@@ -115,7 +734,386 @@ async fn handle_socket(mut socket: WebSocket, app_state: AppState) {
                     None => break,
                 }
             }
+        };
+        for frame in outbound
+            .iter()
+            .filter_map(|outbound| outbound.frame(encoding))
+        {
+            if socket.send(frame).await.is_err() {
+                info!("ws disconnected");
+                return;
+            }
         }
     }
     info!("ws disconnected");
 }
+
+async fn recv_frame(
+    rx: &mut Option<broadcast::Receiver<Arc<FrameBroadcast>>>,
+) -> Result<Arc<FrameBroadcast>, broadcast::error::RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn tick(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+// Broadcast messages after `last_sequence` for this client, or resync_required when the replay
+// buffer no longer holds all of them or the sequence comes from before a server restart.
+fn catch_up(
+    app_state: &AppState,
+    subscription: &Subscription,
+    last_sequence: &mut u64,
+    missed: Option<Vec<Arc<Outbound>>>,
+) -> Vec<Arc<Outbound>> {
+    let resume_from = *last_sequence;
+    let (timestamp_ms, monotonic_ms, sequence) = reply_envelope(app_state);
+    let reason = match missed {
+        _ if resume_from > sequence => "unknown_sequence",
+        Some(missed) => {
+            *last_sequence = missed
+                .iter()
+                .map(|outbound| outbound.sequence)
+                .fold(resume_from, u64::max);
+            return missed
+                .into_iter()
+                .filter(|outbound| subscription.accepts_broadcast(outbound.message_type))
+                .collect();
+        }
+        None => "replay_expired",
+    };
+    warn!(reason, resume_from, "client needs a resync");
+    *last_sequence = sequence;
+    vec![Outbound::new(ResyncRequiredMessage {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms,
+        monotonic_ms,
+        sequence,
+        message_type: "resync_required",
+        reason,
+        resume_from,
+        oldest_sequence: app_state.broadcaster().oldest_sequence(),
+    })]
+}
+
+// Sequence of a shared state_delta keyframe among a connection's replies, 0 when there is none.
+fn keyframe_through(replies: &[Arc<Outbound>]) -> u64 {
+    replies
+        .iter()
+        .filter(|outbound| outbound.message_type == "state_delta")
+        .map(|outbound| outbound.sequence)
+        .max()
+        .unwrap_or(0)
+}
+
+// Replies to a single client reuse the latest broadcast sequence rather than taking a new one,
+// so they do not show up as gaps on other connections.
+fn reply_envelope(app_state: &AppState) -> (u64, u64, u64) {
+    (
+        now_epoch_ms(),
+        monotonic_ms(app_state.start_instant),
+        app_state.sequence.load(Ordering::Relaxed),
+    )
+}
+
+async fn custom_state_update(
+    app_state: &AppState,
+    subscription: &Subscription,
+    state_delta: &mut DeltaEncoder,
+    request_id: Option<String>,
+) -> Option<Arc<Outbound>> {
+    let (state, source_timestamp_ms) = {
+        let store = app_state.store.read().await;
+        if store.session.state.is_empty() {
+            return None;
+        }
+        (
+            subscription.select_state(&store.session.state),
+            store.last_source_timestamp_ms,
+        )
+    };
+    let (timestamp_ms, monotonic_ms, sequence) = reply_envelope(app_state);
+    if !subscription.state_delta {
+        return Some(Outbound::new(StateUpdateMessage {
+            schema_version: SCHEMA_VERSION,
+            timestamp_ms,
+            monotonic_ms,
+            sequence,
+            message_type: "state_update",
+            state,
+            source_timestamp_ms,
+        }));
+    }
+    // Deltas refer to earlier messages by sequence, so this stream numbers its own messages
+    // rather than taking broadcast sequences other connections would see as gaps.
+    let sequence = state_delta
+        .keyframe()
+        .map_or(1, |(sequence, _)| sequence + 1);
+    let delta = state_delta.next(sequence, state, monotonic_ms)?;
+    Some(Outbound::new(StateDeltaMessage {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms,
+        monotonic_ms,
+        sequence,
+        message_type: "state_delta",
+        request_id,
+        keyframe: delta.base_sequence.is_none(),
+        base_sequence: delta.base_sequence,
+        state: delta.state,
+        removed: delta.removed,
+        source_timestamp_ms,
+    }))
+}
+
+async fn custom_samples_window(
+    app_state: &AppState,
+    subscription: &Subscription,
+) -> Option<Arc<Outbound>> {
+    let window = {
+        let store = app_state.store.read().await;
+        let selection = subscription.sample_selection(&store.sample_channels);
+        SamplesWindow::build(&store, &selection, monotonic_ms(app_state.start_instant))?
+    };
+    let (timestamp_ms, monotonic_ms, sequence) = reply_envelope(app_state);
+    Some(Outbound::new(SamplesWindowMessage {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms,
+        monotonic_ms,
+        sequence,
+        message_type: "samples_window",
+        window,
+        decimated: true,
+    }))
+}
+
+// A keyframe for a client that just switched to deltas or lost track of the chain. Shared
+// stream clients get the current baseline, so the next broadcast delta applies on top of it.
+async fn state_keyframe(
+    app_state: &AppState,
+    subscription: &Subscription,
+    state_delta: &mut DeltaEncoder,
+    request_id: Option<String>,
+) -> Option<Arc<Outbound>> {
+    if subscription.custom_state() {
+        state_delta.reset();
+        return custom_state_update(app_state, subscription, state_delta, request_id).await;
+    }
+    let (sequence, state) = app_state.state_delta.baseline()?;
+    let source_timestamp_ms = app_state.store.read().await.last_source_timestamp_ms;
+    Some(Outbound::new(StateDeltaMessage {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms: now_epoch_ms(),
+        monotonic_ms: monotonic_ms(app_state.start_instant),
+        sequence,
+        message_type: "state_delta",
+        request_id,
+        keyframe: true,
+        base_sequence: None,
+        state,
+        removed: Vec::new(),
+        source_timestamp_ms,
+    }))
+}
+
+enum Reply {
+    // Followed by a state_delta keyframe when the client has just switched to deltas.
+    Subscription { keyframe: bool },
+    History(History),
+    Keyframe,
+}
+
+async fn handle_request(
+    app_state: &AppState,
+    subscription: &mut Subscription,
+    state_delta: &mut DeltaEncoder,
+    request: Result<ClientRequest, String>,
+) -> Vec<Arc<Outbound>> {
+    let (timestamp_ms, monotonic_ms, sequence) = reply_envelope(app_state);
+    let (request_id, result) = match request {
+        Ok(request) => {
+            let result = match request.message {
+                ClientMessage::Subscribe { types } => subscription
+                    .subscribe(&types)
+                    .map(|_| Reply::Subscription { keyframe: false }),
+                ClientMessage::Unsubscribe { types } => subscription
+                    .unsubscribe(&types)
+                    .map(|_| Reply::Subscription { keyframe: false }),
+                ClientMessage::ConfigureState {
+                    interval_ms,
+                    fields,
+                    delta,
+                } => subscription
+                    .configure_state(interval_ms, fields, delta)
+                    .map(|_| Reply::Subscription { keyframe: delta }),
+                ClientMessage::ConfigureSamples { channels } => {
+                    let mut store = app_state.store.write().await;
+                    subscription
+                        .configure_samples(channels, &mut store.sample_channels)
+                        .map(|_| Reply::Subscription { keyframe: false })
+                }
+                ClientMessage::ConfigureFrames { fields } => subscription
+                    .configure_frames(fields)
+                    .map(|_| Reply::Subscription { keyframe: false }),
+                ClientMessage::Resync if subscription.state_delta => Ok(Reply::Keyframe),
+                ClientMessage::Resync => Err(
+                    "resync needs state deltas; send configure_state with delta first".to_string(),
+                ),
+                ClientMessage::History { kind, duration_ms } => Ok(Reply::History(
+                    history(app_state, subscription, kind, duration_ms).await,
+                )),
+            };
+            (request.request_id, result)
+        }
+        Err(err) => (None, Err(format!("invalid request: {err}"))),
+    };
+    let error_reply = |request_id, error| {
+        Outbound::new(ErrorMessage {
+            schema_version: SCHEMA_VERSION,
+            timestamp_ms,
+            monotonic_ms,
+            sequence,
+            message_type: "error",
+            request_id,
+            error,
+        })
+    };
+    match result {
+        Ok(Reply::History(history)) => vec![Outbound::new(HistoryMessage {
+            schema_version: SCHEMA_VERSION,
+            timestamp_ms,
+            monotonic_ms,
+            sequence,
+            message_type: "history",
+            request_id,
+            history,
+        })],
+        Ok(Reply::Subscription { keyframe }) => {
+            info!(subscription = ?subscription, "ws subscription changed");
+            let mut replies = vec![Outbound::new(SubscriptionMessage {
+                schema_version: SCHEMA_VERSION,
+                timestamp_ms,
+                monotonic_ms,
+                sequence,
+                message_type: "subscription",
+                request_id,
+                subscription: subscription.clone(),
+            })];
+            if keyframe {
+                replies.extend(state_keyframe(app_state, subscription, state_delta, None).await);
+            }
+            replies
+        }
+        Ok(Reply::Keyframe) => {
+            match state_keyframe(app_state, subscription, state_delta, request_id.clone()).await {
+                Some(keyframe) => vec![keyframe],
+                None => vec![error_reply(
+                    request_id,
+                    "no telemetry state yet".to_string(),
+                )],
+            }
+        }
+        Err(error) => vec![error_reply(request_id, error)],
+    }
+}
+
+async fn history(
+    app_state: &AppState,
+    subscription: &Subscription,
+    kind: HistoryKind,
+    duration_ms: Option<u64>,
+) -> History {
+    let store = app_state.store.read().await;
+    match kind {
+        HistoryKind::Samples => {
+            let end_ms = monotonic_ms(app_state.start_instant);
+            let start_ms = end_ms.saturating_sub(duration_ms.unwrap_or(WINDOW_DURATION_MS));
+            let samples = store.samples.to_vec_ordered();
+            History::Samples {
+                start_ms,
+                end_ms,
+                samples: store.sample_channels.project(
+                    &subscription.sample_selection(&store.sample_channels),
+                    samples.iter().filter(|sample| sample.t_ms >= start_ms),
+                ),
+            }
+        }
+        HistoryKind::Events => History::Events {
+            events: app_state.broadcaster().event_history(),
+        },
+        HistoryKind::Laps => History::Laps {
+            laps: store
+                .lap_history
+                .iter()
+                .map(|lap| lap.summary(lap.id.to_string()))
+                .collect(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn broadcaster() -> Broadcaster {
+        let (tx, _) = broadcast::channel(16);
+        let (frame_tx, _) = broadcast::channel(16);
+        Broadcaster {
+            tx,
+            sequence: Arc::new(AtomicU64::new(0)),
+            frame_sequence: Arc::new(AtomicU64::new(0)),
+            start_instant: Instant::now(),
+            replay: Arc::new(Mutex::new(ReplayBuffer::new())),
+            frame_tx,
+            events: Arc::new(Mutex::new(VecDeque::new())),
+            state_delta: SharedDelta::new(),
+        }
+    }
+
+    fn send_lag(broadcaster: &Broadcaster) -> u64 {
+        broadcaster.send(|sequence| LagMessage {
+            schema_version: SCHEMA_VERSION,
+            timestamp_ms: 0,
+            monotonic_ms: 0,
+            sequence,
+            message_type: "lag",
+            channel: "broadcast",
+            skipped: 0,
+        })
+    }
+
+    #[test]
+    fn send_only_takes_a_sequence_for_built_messages() {
+        let broadcaster = broadcaster();
+        assert_eq!(send_lag(&broadcaster), 1);
+        assert_eq!(broadcaster.send_with(|_| None::<LagMessage>), None);
+        assert_eq!(send_lag(&broadcaster), 2);
+    }
+
+    #[test]
+    fn resume_replays_missed_messages_then_continues_live() {
+        let broadcaster = broadcaster();
+        for _ in 0..3 {
+            send_lag(&broadcaster);
+        }
+        let (mut rx, missed) = broadcaster.resume(1);
+        let missed: Vec<u64> = missed
+            .expect("replay still holds the messages")
+            .iter()
+            .map(|outbound| outbound.sequence)
+            .collect();
+        assert_eq!(missed, vec![2, 3]);
+        assert!(rx.try_recv().is_err());
+
+        send_lag(&broadcaster);
+        let live = rx.try_recv().expect("live message after the replay");
+        assert_eq!(live.sequence, 4);
+    }
+}
@@ -0,0 +1,108 @@
+// Bounded history of broadcast messages so reconnecting or lagging clients can catch up by sequence.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::constants::{REPLAY_BUFFER_CAP, REPLAY_WINDOW_MS};
+
+use super::Outbound;
+
+pub struct ReplayBuffer {
+    // Kept in send order alongside the monotonic time each message was sent.
+    messages: VecDeque<(u64, Arc<Outbound>)>,
+    // Highest sequence dropped from the buffer; resuming at or before it would miss messages.
+    evicted_through: u64,
+}
+
+impl ReplayBuffer {
+    pub fn new() -> Self {
+        Self {
+            messages: VecDeque::with_capacity(REPLAY_BUFFER_CAP),
+            evicted_through: 0,
+        }
+    }
+
+    pub fn push(&mut self, outbound: Arc<Outbound>, now_ms: u64) {
+        self.messages.push_back((now_ms, outbound));
+        while let Some((sent_ms, outbound)) = self.messages.front() {
+            let expired = now_ms.saturating_sub(*sent_ms) > REPLAY_WINDOW_MS;
+            if !expired && self.messages.len() <= REPLAY_BUFFER_CAP {
+                break;
+            }
+            self.evicted_through = self.evicted_through.max(outbound.sequence);
+            self.messages.pop_front();
+        }
+    }
+
+    pub fn oldest_sequence(&self) -> Option<u64> {
+        self.messages
+            .iter()
+            .map(|(_, outbound)| outbound.sequence)
+            .min()
+    }
+
+    // Everything broadcast after `sequence`, or None when part of it has already been dropped.
+    pub fn since(&self, sequence: u64) -> Option<Vec<Arc<Outbound>>> {
+        if sequence < self.evicted_through {
+            return None;
+        }
+        Some(
+            self.messages
+                .iter()
+                .filter(|(_, outbound)| outbound.sequence > sequence)
+                .map(|(_, outbound)| outbound.clone())
+                .collect(),
+        )
+    }
+}
+
+impl Default for ReplayBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::SCHEMA_VERSION;
+    use crate::ws::LagMessage;
+
+    fn outbound(sequence: u64) -> Arc<Outbound> {
+        Outbound::new(LagMessage {
+            schema_version: SCHEMA_VERSION,
+            timestamp_ms: 0,
+            monotonic_ms: 0,
+            sequence,
+            message_type: "lag",
+            channel: "broadcast",
+            skipped: 0,
+        })
+    }
+
+    fn sequences(messages: Option<Vec<Arc<Outbound>>>) -> Option<Vec<u64>> {
+        messages.map(|messages| messages.iter().map(|outbound| outbound.sequence).collect())
+    }
+
+    #[test]
+    fn since_returns_messages_after_the_sequence() {
+        let mut replay = ReplayBuffer::new();
+        for sequence in 1..=4 {
+            replay.push(outbound(sequence), 0);
+        }
+        assert_eq!(sequences(replay.since(2)), Some(vec![3, 4]));
+        assert_eq!(sequences(replay.since(0)), Some(vec![1, 2, 3, 4]));
+        assert_eq!(sequences(replay.since(4)), Some(vec![]));
+    }
+
+    #[test]
+    fn since_fails_once_needed_messages_expire() {
+        let mut replay = ReplayBuffer::new();
+        replay.push(outbound(1), 0);
+        replay.push(outbound(2), 0);
+        replay.push(outbound(3), REPLAY_WINDOW_MS + 1);
+        assert_eq!(replay.oldest_sequence(), Some(3));
+        assert_eq!(sequences(replay.since(1)), None);
+        assert_eq!(sequences(replay.since(2)), Some(vec![3]));
+    }
+}
@@ -0,0 +1,267 @@
+// Server-Sent Events transport: the WebSocket broadcast stream for clients that only read.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Query, State as AxumState};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures::{stream, Stream};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::broadcast;
+use tokio::time::{self, Interval, MissedTickBehavior};
+use tracing::{info, warn};
+
+use super::{
+    catch_up, custom_samples_window, custom_state_update, reply_envelope, state_keyframe, tick,
+    DeltaEncoder, DeltaListener, Encoding, LagMessage, Outbound, Subscription, SubscriptionMessage,
+};
+use crate::app::AppState;
+use crate::constants::SCHEMA_VERSION;
+
+// The same settings a WebSocket client sends as control messages; lists are comma-separated.
+#[derive(Deserialize)]
+pub struct SseQuery {
+    pub types: Option<String>,
+    pub interval_ms: Option<u64>,
+    pub fields: Option<String>,
+    #[serde(default)]
+    pub delta: bool,
+    pub channels: Option<String>,
+    // For the first connection; EventSource reconnects send Last-Event-ID instead.
+    pub resume_from: Option<u64>,
+}
+
+pub async fn sse_handler(
+    AxumState(app_state): AxumState<AppState>,
+    Query(query): Query<SseQuery>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<SseEvent, Infallible>>>, Response> {
+    let subscription = sse_subscription(
+        &app_state,
+        query.types,
+        query.interval_ms,
+        query.fields,
+        query.delta,
+        query.channels,
+    )
+    .await
+    .map_err(|error| (StatusCode::BAD_REQUEST, Json(json!({ "error": error }))).into_response())?;
+    let resume_from = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .or(query.resume_from);
+    info!(resume_from, subscription = ?subscription, "sse connected");
+
+    let broadcaster = app_state.broadcaster();
+    let (rx, missed) = match resume_from {
+        Some(sequence) => {
+            let (rx, missed) = broadcaster.resume(sequence);
+            (rx, Some(missed))
+        }
+        None => (app_state.tx.subscribe(), None),
+    };
+    let state_timer =
+        (subscription.custom_state() && subscription.wants("state_update")).then(|| {
+            let mut timer = time::interval(Duration::from_millis(subscription.state_interval_ms));
+            timer.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            timer
+        });
+    let mut delta_listener = app_state.state_delta.listener();
+    delta_listener.update(&subscription);
+    let mut connection = SseConnection {
+        last_sequence: resume_from.unwrap_or_else(|| app_state.sequence.load(Ordering::Relaxed)),
+        app_state,
+        subscription,
+        rx,
+        state_timer,
+        state_delta: DeltaEncoder::new(),
+        _delta_listener: delta_listener,
+        keyframe_sequence: 0,
+        pending: VecDeque::new(),
+    };
+
+    let (timestamp_ms, monotonic_ms, sequence) = reply_envelope(&connection.app_state);
+    connection.push_reply(Outbound::new(SubscriptionMessage {
+        schema_version: SCHEMA_VERSION,
+        timestamp_ms,
+        monotonic_ms,
+        sequence,
+        message_type: "subscription",
+        request_id: None,
+        subscription: connection.subscription.clone(),
+    }));
+    // Replayed deltas are relative to older keyframes, so the fresh keyframe goes after them.
+    if let Some(missed) = missed {
+        connection.catch_up(missed);
+    }
+    if connection.subscription.state_delta {
+        if let Some(keyframe) = state_keyframe(
+            &connection.app_state,
+            &connection.subscription,
+            &mut connection.state_delta,
+            None,
+        )
+        .await
+        {
+            if !connection.subscription.custom_state() {
+                connection.keyframe_sequence = keyframe.sequence;
+            }
+            connection.push_reply(keyframe);
+        }
+    }
+
+    let events = stream::unfold(connection, |mut connection| async move {
+        loop {
+            if let Some((outbound, id)) = connection.pending.pop_front() {
+                let Some(event) = sse_event(&outbound, id) else {
+                    continue;
+                };
+                return Some((Ok(event), connection));
+            }
+            if !connection.receive().await {
+                return None;
+            }
+        }
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+async fn sse_subscription(
+    app_state: &AppState,
+    types: Option<String>,
+    interval_ms: Option<u64>,
+    fields: Option<String>,
+    delta: bool,
+    channels: Option<String>,
+) -> Result<Subscription, String> {
+    let mut subscription = Subscription::new();
+    if let Some(types) = split_list(types) {
+        subscription.types.clear();
+        subscription.subscribe(&types)?;
+    }
+    if subscription.wants("frame") {
+        return Err("frame messages are only available over WebSocket".to_string());
+    }
+    subscription.configure_state(interval_ms, split_list(fields), delta)?;
+    if let Some(channels) = split_list(channels) {
+        let mut store = app_state.store.write().await;
+        subscription.configure_samples(Some(channels), &mut store.sample_channels)?;
+    }
+    Ok(subscription)
+}
+
+fn split_list(value: Option<String>) -> Option<Vec<String>> {
+    value.map(|value| {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+}
+
+// Only messages from the shared broadcast carry an SSE id, so Last-Event-ID always names a
+// sequence the replay buffer knows; replies for this connection alone leave it unchanged.
+fn sse_event(outbound: &Outbound, id: Option<u64>) -> Option<SseEvent> {
+    let data = std::str::from_utf8(outbound.encoded(Encoding::Json)?).ok()?;
+    let event = SseEvent::default().event(outbound.message_type).data(data);
+    Some(match id {
+        Some(id) => event.id(id.to_string()),
+        None => event,
+    })
+}
+
+struct SseConnection {
+    app_state: AppState,
+    subscription: Subscription,
+    rx: broadcast::Receiver<Arc<Outbound>>,
+    // Highest broadcast sequence this connection has seen, delivered or filtered out.
+    last_sequence: u64,
+    state_timer: Option<Interval>,
+    state_delta: DeltaEncoder,
+    _delta_listener: DeltaListener,
+    // Sequence of the shared keyframe sent on connect; broadcast deltas up to it are already in it.
+    keyframe_sequence: u64,
+    pending: VecDeque<(Arc<Outbound>, Option<u64>)>,
+}
+
+impl SseConnection {
+    fn push_reply(&mut self, outbound: Arc<Outbound>) {
+        self.pending.push_back((outbound, None));
+    }
+
+    fn catch_up(&mut self, missed: Option<Vec<Arc<Outbound>>>) {
+        for outbound in catch_up(
+            &self.app_state,
+            &self.subscription,
+            &mut self.last_sequence,
+            missed,
+        ) {
+            let id = outbound.sequence;
+            self.pending.push_back((outbound, Some(id)));
+        }
+    }
+
+    // Queues whatever the next broadcast or timer tick produces; false once the server shuts down.
+    async fn receive(&mut self) -> bool {
+        tokio::select! {
+            outbound = self.rx.recv() => match outbound {
+                Ok(outbound) => {
+                    self.last_sequence = self.last_sequence.max(outbound.sequence);
+                    let in_keyframe = outbound.message_type == "state_delta"
+                        && outbound.sequence <= self.keyframe_sequence;
+                    if outbound.message_type == "samples_window" && self.subscription.custom_samples() {
+                        if let Some(window) = custom_samples_window(&self.app_state, &self.subscription).await {
+                            self.push_reply(window);
+                        }
+                    } else if !in_keyframe && self.subscription.accepts_broadcast(outbound.message_type) {
+                        let id = outbound.sequence;
+                        self.pending.push_back((outbound, Some(id)));
+                    }
+                    true
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped, last_sequence = self.last_sequence, "sse client lagged");
+                    let (next_rx, missed) = self.app_state.broadcaster().resume(self.last_sequence);
+                    self.rx = next_rx;
+                    let (timestamp_ms, monotonic_ms, sequence) = reply_envelope(&self.app_state);
+                    self.push_reply(Outbound::new(LagMessage {
+                        schema_version: SCHEMA_VERSION,
+                        timestamp_ms,
+                        monotonic_ms,
+                        sequence,
+                        message_type: "lag",
+                        channel: "broadcast",
+                        skipped,
+                    }));
+                    self.catch_up(missed);
+                    true
+                }
+                Err(broadcast::error::RecvError::Closed) => false,
+            },
+            _ = tick(&mut self.state_timer) => {
+                if let Some(update) =
+                    custom_state_update(&self.app_state, &self.subscription, &mut self.state_delta, None).await
+                {
+                    self.push_reply(update);
+                }
+                true
+            }
+        }
+    }
+}
+
+impl Drop for SseConnection {
+    fn drop(&mut self) {
+        info!("sse disconnected");
+    }
+}
@@ -0,0 +1,330 @@
+// Client-selected message types, state_update rate and field selections for one connection.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::encoding::json_map;
+use super::{EventMessage, FrameBroadcast, Outbound};
+use crate::analysis::LapSummary;
+use crate::constants::STATE_INTERVAL_MS;
+use crate::model::{
+    is_state_field, ChannelLease, ChannelSamples, SampleChannels, State as TelemetryState,
+    TelemetryFrame,
+};
+
+// Message types a client can subscribe to; replies to its own requests are always delivered.
+pub const SUBSCRIBABLE_TYPES: [&str; 10] = [
+    "state_update",
+    "samples_window",
+    "personal_best",
+    "tyre_event",
+    "tyre_temperature_alert",
+    "performance_test",
+    "event",
+    "link_status",
+    "packet_stats",
+    "frame",
+];
+// Left out of new subscriptions until the client asks for them.
+pub const OPT_IN_TYPES: [&str; 2] = ["packet_stats", "frame"];
+pub const MAX_STATE_INTERVAL_MS: u64 = 10_000;
+
+#[derive(Deserialize)]
+pub struct ClientRequest {
+    #[serde(default)]
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub message: ClientMessage,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Subscribe {
+        types: Vec<String>,
+    },
+    Unsubscribe {
+        types: Vec<String>,
+    },
+    // Replaces both settings: omitted values go back to the server defaults.
+    ConfigureState {
+        interval_ms: Option<u64>,
+        fields: Option<Vec<String>>,
+        #[serde(default)]
+        delta: bool,
+    },
+    // Asks for a state_delta keyframe after a gap in the base_sequence chain.
+    Resync,
+    // Builds samples_window and sample history from these channels; omitted uses the server's.
+    ConfigureSamples {
+        channels: Option<Vec<String>>,
+    },
+    // Limits frame messages to the listed TelemetryFrame fields; omitted sends every field.
+    ConfigureFrames {
+        fields: Option<Vec<String>>,
+    },
+    History {
+        kind: HistoryKind,
+        duration_ms: Option<u64>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryKind {
+    Samples,
+    Laps,
+    Events,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum History {
+    Samples {
+        start_ms: u64,
+        end_ms: u64,
+        #[serde(flatten)]
+        samples: ChannelSamples,
+    },
+    Laps {
+        laps: Vec<LapSummary>,
+    },
+    Events {
+        events: Vec<EventMessage>,
+    },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Subscription {
+    pub types: BTreeSet<&'static str>,
+    pub state_interval_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_fields: Option<BTreeSet<String>>,
+    // Send state as state_delta keyframes and changes instead of full state_update messages.
+    pub state_delta: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_fields: Option<BTreeSet<String>>,
+    // In the order the client listed them, which is also the order in each sample.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_channels: Option<Vec<String>>,
+    // Keeps those channels recording for as long as this connection holds the subscription.
+    #[serde(skip)]
+    sample_leases: Vec<ChannelLease>,
+}
+
+impl Subscription {
+    pub fn new() -> Self {
+        Self {
+            types: SUBSCRIBABLE_TYPES
+                .into_iter()
+                .filter(|message_type| !OPT_IN_TYPES.contains(message_type))
+                .collect(),
+            state_interval_ms: STATE_INTERVAL_MS,
+            state_fields: None,
+            state_delta: false,
+            frame_fields: None,
+            sample_channels: None,
+            sample_leases: Vec::new(),
+        }
+    }
+
+    pub fn wants(&self, message_type: &str) -> bool {
+        self.types.contains(message_type) || !SUBSCRIBABLE_TYPES.contains(&message_type)
+    }
+
+    // The shared state broadcasts only fit clients on the default rate and fields.
+    pub fn custom_state(&self) -> bool {
+        self.state_interval_ms != STATE_INTERVAL_MS || self.state_fields.is_some()
+    }
+
+    pub fn custom_samples(&self) -> bool {
+        self.wants("samples_window") && self.sample_channels.is_some()
+    }
+
+    // Whether a message from the shared broadcast goes to this client.
+    pub fn accepts_broadcast(&self, message_type: &str) -> bool {
+        match message_type {
+            "samples_window" => self.wants(message_type) && self.sample_channels.is_none(),
+            "state_update" | "state_delta" => {
+                self.wants("state_update")
+                    && !self.custom_state()
+                    && (message_type == "state_delta") == self.state_delta
+            }
+            _ => self.wants(message_type),
+        }
+    }
+
+    pub fn subscribe(&mut self, types: &[String]) -> Result<(), String> {
+        for message_type in resolve_types(types)? {
+            self.types.insert(message_type);
+        }
+        Ok(())
+    }
+
+    pub fn unsubscribe(&mut self, types: &[String]) -> Result<(), String> {
+        for message_type in resolve_types(types)? {
+            self.types.remove(message_type);
+        }
+        Ok(())
+    }
+
+    pub fn configure_state(
+        &mut self,
+        interval_ms: Option<u64>,
+        fields: Option<Vec<String>>,
+        delta: bool,
+    ) -> Result<(), String> {
+        let interval_ms = interval_ms.unwrap_or(STATE_INTERVAL_MS);
+        if !(STATE_INTERVAL_MS..=MAX_STATE_INTERVAL_MS).contains(&interval_ms) {
+            return Err(format!(
+                "interval_ms must be between {STATE_INTERVAL_MS} and {MAX_STATE_INTERVAL_MS}"
+            ));
+        }
+        if fields.as_ref().is_some_and(|fields| fields.is_empty()) {
+            return Err(
+                "fields must not be empty; unsubscribe from state_update instead".to_string(),
+            );
+        }
+        if let Some(fields) = fields.as_ref() {
+            check_fields(fields, "state", is_state_field)?;
+        }
+        self.state_interval_ms = interval_ms;
+        self.state_fields = fields.map(|fields| fields.into_iter().collect());
+        self.state_delta = delta;
+        Ok(())
+    }
+
+    // Registers the channels with the sample buffer, which records them from now on; the
+    // previous selection's channels stop recording unless someone else still uses them.
+    pub fn configure_samples(
+        &mut self,
+        channels: Option<Vec<String>>,
+        recorded: &mut SampleChannels,
+    ) -> Result<(), String> {
+        self.sample_leases = match channels.as_ref() {
+            Some(channels) => recorded.select(channels)?,
+            None => Vec::new(),
+        };
+        self.sample_channels = channels;
+        Ok(())
+    }
+
+    pub fn sample_selection(&self, recorded: &SampleChannels) -> Vec<usize> {
+        match self.sample_channels.as_ref() {
+            Some(_) => self.sample_leases.iter().map(ChannelLease::index).collect(),
+            None => recorded.defaults(),
+        }
+    }
+
+    pub fn configure_frames(&mut self, fields: Option<Vec<String>>) -> Result<(), String> {
+        if fields.as_ref().is_some_and(|fields| fields.is_empty()) {
+            return Err("fields must not be empty; unsubscribe from frame instead".to_string());
+        }
+        if let Some(fields) = fields.as_ref() {
+            check_fields(fields, "frame", is_frame_field)?;
+        }
+        self.frame_fields = fields.map(|fields| fields.into_iter().collect());
+        Ok(())
+    }
+
+    // The shared encoding when every field is wanted, otherwise a trimmed copy for this client.
+    pub fn select_frame(&self, frame: &FrameBroadcast) -> Arc<Outbound> {
+        let Some(fields) = self.frame_fields.as_ref() else {
+            return frame.outbound.clone();
+        };
+        let selected: Map<String, Value> = json_map(&frame.message.frame)
+            .into_iter()
+            .filter(|(key, _)| fields.contains(key))
+            .collect();
+        Outbound::new(frame.message.with_frame(selected))
+    }
+
+    // Fields the state does not currently carry are left out, as in the full update.
+    pub fn select_state(&self, state: &TelemetryState) -> Map<String, Value> {
+        let map = json_map(state);
+        match self.state_fields.as_ref() {
+            Some(fields) => map
+                .into_iter()
+                .filter(|(key, _)| fields.contains(key))
+                .collect(),
+            None => map,
+        }
+    }
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn resolve_types(types: &[String]) -> Result<Vec<&'static str>, String> {
+    types
+        .iter()
+        .map(|name| {
+            SUBSCRIBABLE_TYPES
+                .into_iter()
+                .find(|known| *known == name)
+                .ok_or_else(|| format!("unknown message type '{name}'"))
+        })
+        .collect()
+}
+
+fn check_fields(fields: &[String], kind: &str, known: fn(&str) -> bool) -> Result<(), String> {
+    match fields.iter().find(|name| !known(name)) {
+        Some(name) => Err(format!("unknown {kind} field '{name}'")),
+        None => Ok(()),
+    }
+}
+
+// Every frame field is optional and always serialized, so an empty frame lists them all.
+fn is_frame_field(name: &str) -> bool {
+    static FIELDS: OnceLock<BTreeSet<String>> = OnceLock::new();
+    FIELDS
+        .get_or_init(|| {
+            serde_json::from_value::<TelemetryFrame>(Value::Object(Map::new()))
+                .map(|frame| json_map(&frame).keys().cloned().collect())
+                .unwrap_or_default()
+        })
+        .contains(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(fields: &[&str]) -> Option<Vec<String>> {
+        Some(fields.iter().map(|field| field.to_string()).collect())
+    }
+
+    #[test]
+    fn unknown_field_names_are_rejected() {
+        let mut subscription = Subscription::new();
+
+        assert!(subscription
+            .configure_state(None, names(&["speed_kph", "lap_progress"]), false)
+            .is_ok());
+        assert_eq!(
+            subscription.configure_state(None, names(&["speed_kph", "speed"]), false),
+            Err("unknown state field 'speed'".to_string())
+        );
+
+        assert!(subscription
+            .configure_frames(names(&["rpm", "throttle"]))
+            .is_ok());
+        assert_eq!(
+            subscription.configure_frames(names(&["rpm", "lap_progress"])),
+            Err("unknown frame field 'lap_progress'".to_string())
+        );
+        let kept: Vec<&str> = subscription
+            .frame_fields
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(kept, ["rpm", "throttle"]);
+    }
+}
@@ -163,6 +163,28 @@ pub struct State {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fuel_laps_remaining: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub personal_best_ms: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta_ms: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub predicted_lap_ms: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lap_distance_m: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lap_progress: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sector_index: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sector_delta_ms: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theoretical_best_ms: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_drive_ratio: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_at_limiter_kph: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theoretical_top_speed_kph: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub pos_x: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pos_y: Option<f32>,
@@ -398,6 +420,17 @@ impl State {
             track_id,
             avg_fuel_consume_pct_per_lap,
             fuel_laps_remaining,
+            personal_best_ms,
+            delta_ms,
+            predicted_lap_ms,
+            lap_distance_m,
+            lap_progress,
+            sector_index,
+            sector_delta_ms,
+            theoretical_best_ms,
+            final_drive_ratio,
+            speed_at_limiter_kph,
+            theoretical_top_speed_kph,
             flags_8e,
             flags_8f,
             flags_93,
@@ -19,9 +19,16 @@ pub struct SessionTransition {
     pub to: SessionState,
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct LapCompletion {
+    pub lap_number: i16,
+    pub lap_time_ms: i32,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct SessionEvents {
     pub transition: Option<SessionTransition>,
+    pub lap_completed: Option<LapCompletion>,
     pub should_stop_record: bool,
     pub should_start_record: bool,
 }
@@ -126,13 +133,22 @@ pub fn apply_frame(
 
     fields.state.update_from(frame);
 
+    let mut lap_completed = None;
     if let Some(last_lap_ms) = frame.last_lap_ms {
         if *fields.last_lap_time_ms_recorded != Some(last_lap_ms) {
+            let first_observation = fields.last_lap_time_ms_recorded.is_none();
             *fields.last_lap_time_ms_recorded = Some(last_lap_ms);
             if *fields.session_state != SessionState::NotInRace {
                 *fields.lap_start_mono_ms = Some(now_ms);
                 *fields.lap_pause_started_ms = None;
                 *fields.lap_pause_accum_ms = 0;
+                // The first value seen after a reset may be stale from a previous race.
+                if !first_observation && last_lap_ms > 0 {
+                    lap_completed = Some(LapCompletion {
+                        lap_number: fields.last_current_lap.unwrap_or(0),
+                        lap_time_ms: last_lap_ms,
+                    });
+                }
             }
         }
     }
@@ -239,6 +255,7 @@ pub fn apply_frame(
 
     SessionEvents {
         transition,
+        lap_completed,
         should_stop_record,
         should_start_record,
     }